@@ -1,127 +1,146 @@
-use helpers::input_lines;
-use std::cmp::Ordering;
-use std::collections::VecDeque;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Number of previous values a number has to be the sum of
+    #[arg(long, default_value_t = 25)]
+    preamble: usize,
+
+    /// Prints all the numbers that are not the sum of two previous values
+    #[arg(long)]
+    all_invalid: bool,
+}
+
+/// Sliding window of the last `capacity` values, keeping track of the sums of their pairs.
+///
+/// Adding a value updates the sums in `O(capacity)`, checking a sum is `O(1)`.
 #[derive(Debug)]
-struct CircularBuffer<T> {
-    values: VecDeque<T>,
+struct PairSumIndex {
+    values: VecDeque<i64>,
     capacity: usize,
+    /// Number of pairs of different values in the window with a given sum
+    sums: HashMap<i64, usize>,
 }
 
-impl<T> CircularBuffer<T> {
+impl PairSumIndex {
     fn new(capacity: usize) -> Self {
         Self {
-            values: VecDeque::new(),
+            values: VecDeque::with_capacity(capacity),
             capacity,
+            sums: HashMap::new(),
         }
     }
 
-    fn add(&mut self, value: T) {
+    fn add(&mut self, value: i64) {
         if self.values.len() >= self.capacity {
-            self.values.pop_front();
+            if let Some(removed) = self.values.pop_front() {
+                for other in self.values.iter().filter(|other| **other != removed) {
+                    if let Some(count) = self.sums.get_mut(&(removed + other)) {
+                        *count -= 1;
+                        if *count == 0 {
+                            self.sums.remove(&(removed + other));
+                        }
+                    }
+                }
+            }
+        }
+        for other in self.values.iter().filter(|other| **other != value) {
+            *self.sums.entry(value + other).or_default() += 1;
         }
         self.values.push_back(value);
     }
 
-    fn iter(&self) -> impl Iterator<Item = &T> {
-        self.values.iter()
+    fn contains_sum(&self, target: i64) -> bool {
+        self.sums.contains_key(&target)
     }
 }
 
-fn two_sum<'a, I: Iterator<Item = &'a i64>>(values_iter: I, target: i64) -> bool {
-    let mut values: Vec<_> = values_iter.collect();
-    values.sort();
-
-    let mut left_index = 0;
-    let mut right_index = values.len() - 1;
-
-    while left_index != right_index {
-        let left = values[left_index];
-        let right = values[right_index];
-        match (left + right).cmp(&target) {
-            Ordering::Equal => {
-                return true;
-            }
-            Ordering::Less => {
-                left_index += 1;
-            }
-            Ordering::Greater => {
-                right_index -= 1;
-            }
+/// Positions of the values (after the preamble) that are not the sum of two different
+/// values among the `preamble` previous ones
+fn invalid_positions(values: &[i64], preamble: usize) -> Vec<usize> {
+    let mut index = PairSumIndex::new(preamble);
+    let mut invalid = vec![];
+    for (position, value) in values.iter().enumerate() {
+        if position >= preamble && !index.contains_sum(*value) {
+            invalid.push(position);
         }
+        index.add(*value);
     }
-
-    false
+    invalid
 }
 
-fn contigous_numbers_with_sum(values: &[&i64], target: i64) -> Vec<i64> {
-    for first_index in 0..values.len() - 1 {
-        for last_index in (first_index + 1)..values.len() {
-            if values[first_index..=last_index]
-                .iter()
-                .map(|value| **value)
-                .sum::<i64>()
-                == target
-            {
-                return values[first_index..=last_index]
-                    .iter()
-                    .map(|value| **value)
-                    .collect();
-            }
+/// Range of at least two contiguous values adding up to `target`.
+///
+/// Prefix sums turn the search into looking for two prefixes differing by `target`,
+/// which works also with negative values.
+fn contiguous_range_with_sum(values: &[i64], target: i64) -> Option<Range<usize>> {
+    // Position of the first prefix (sum of the values before the position) with a given sum
+    let mut prefix_positions: HashMap<i64, usize> = HashMap::new();
+    let mut prefix_sums = vec![0];
+    for (position, value) in values.iter().enumerate() {
+        prefix_sums.push(prefix_sums[position] + value);
+        if position == 0 {
+            continue;
+        }
+        // Ranges ending at `position` need to start at most at `position - 1`
+        prefix_positions
+            .entry(prefix_sums[position - 1])
+            .or_insert(position - 1);
+        if let Some(start) = prefix_positions.get(&(prefix_sums[position + 1] - target)) {
+            return Some(*start..position + 1);
         }
     }
-    vec![0; 2]
+    None
 }
 
-fn part01(values: &[i64]) -> i64 {
-    const COUNT: usize = 25;
-    let mut buffer = CircularBuffer::<i64>::new(COUNT);
-    values.iter().take(COUNT).for_each(|value| {
-        buffer.add(*value);
-    });
-
-    for value in values.iter().skip(COUNT) {
-        if !two_sum(buffer.iter(), *value) {
-            return *value;
-        }
-        buffer.add(*value);
-    }
-    0
+fn first_invalid(values: &[i64], preamble: usize) -> anyhow::Result<i64> {
+    invalid_positions(values, preamble)
+        .first()
+        .map(|position| values[*position])
+        .ok_or_else(|| anyhow::anyhow!("All the numbers are valid"))
 }
 
-fn part02(values: &[i64]) -> i64 {
-    const COUNT: usize = 25;
-    let mut buffer = CircularBuffer::<i64>::new(COUNT);
+fn part01(values: &[i64], preamble: usize) -> anyhow::Result<i64> {
+    first_invalid(values, preamble)
+}
 
-    values.iter().take(COUNT).for_each(|value| {
-        buffer.add(*value);
-    });
+fn part02(values: &[i64], preamble: usize) -> anyhow::Result<i64> {
+    let target = first_invalid(values, preamble)?;
+    let range = contiguous_range_with_sum(values, target)
+        .ok_or_else(|| anyhow::anyhow!("No contiguous range of numbers adds up to {}", target))?;
+    let min = values[range.clone()].iter().min().copied().unwrap_or(0);
+    let max = values[range].iter().max().copied().unwrap_or(0);
+    Ok(min + max)
+}
 
-    for (idx, value) in values.iter().skip(COUNT).enumerate() {
-        if !two_sum(buffer.iter(), *value) {
-            let contigous_numbers = contigous_numbers_with_sum(
-                values
-                    .iter()
-                    .take(idx + COUNT)
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-                *value,
+fn main() -> anyhow::Result<()> {
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let values: Vec<i64> = lines
+        .iter()
+        .enumerate()
+        .map(|(line_no, line)| {
+            line.parse()
+                .map_err(|error| anyhow::anyhow!("Line {}: {} ({})", line_no + 1, line, error))
+        })
+        .collect::<Result<_, _>>()?;
+    anyhow::ensure!(opts.preamble > 1, "The preamble needs at least 2 numbers");
+
+    if opts.all_invalid {
+        for position in invalid_positions(&values, opts.preamble) {
+            println!(
+                "Invalid number at line {}: {}",
+                position + 1,
+                values[position]
             );
-            return contigous_numbers.iter().min().unwrap()
-                + contigous_numbers.iter().max().unwrap();
         }
-        buffer.add(*value);
     }
-    0
-}
-
-fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let values: Vec<i64> = lines.iter().map(|line| line.parse().unwrap()).collect();
 
-    println!("Part 1: {}", part01(&values));
-    println!("Part 2: {}", part02(&values));
+    println!("Part 1: {}", part01(&values, opts.preamble)?);
+    println!("Part 2: {}", part02(&values, opts.preamble)?);
     Ok(())
 }
@@ -1,99 +1,182 @@
-use helpers::input_lines;
-use std::collections::HashMap;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use num_bigint::BigUint;
+use std::collections::{BTreeMap, BTreeSet};
 
 const INPUT: &str = include_str!("../input.txt");
 
-fn part01(sorted_adapers: &[usize]) -> usize {
-    let mut diff_1 = 0;
-    let mut diff_3 = 0;
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Joltage differences accepted between chained adapters (comma separated).
+    /// The device is rated for the highest adapter plus the biggest gap
+    #[arg(long, value_delimiter = ',', default_values_t = [1, 2, 3])]
+    gaps: Vec<u64>,
 
-    let mut current_value = &0;
+    /// Prints the number of differences of each size in the chain of all the adapters
+    #[arg(long)]
+    histogram: bool,
 
-    for value in sorted_adapers {
-        match value - current_value {
-            1 => {
-                diff_1 += 1;
-                current_value = value;
-            }
-            3 => {
-                diff_3 += 1;
-                current_value = value;
-            }
-            _ => {}
+    /// Lists the valid arrangements, up to the given count if any
+    #[arg(long)]
+    arrangements: Option<Option<usize>>,
+}
+
+#[derive(Debug)]
+struct ChainAnalyser {
+    /// Joltages of the outlet, of the adapters (sorted) and of the device
+    joltages: Vec<u64>,
+    gaps: BTreeSet<u64>,
+    /// Number of arrangements from each joltage to the device
+    arrangement_counts: Vec<BigUint>,
+}
+
+impl ChainAnalyser {
+    fn new(adapters: &[u64], gaps: &[u64]) -> anyhow::Result<Self> {
+        let gaps: BTreeSet<u64> = gaps.iter().copied().collect();
+        anyhow::ensure!(!gaps.contains(&0), "Gaps are expected to be positive");
+        let max_gap = gaps
+            .last()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("At least one gap is needed"))?;
+
+        let mut joltages: Vec<u64> = std::iter::once(0).chain(adapters.iter().copied()).collect();
+        joltages.sort_unstable();
+        let device = joltages[joltages.len() - 1] + max_gap;
+        joltages.push(device);
+
+        let mut arrangement_counts = vec![BigUint::default(); joltages.len()];
+        arrangement_counts[joltages.len() - 1] = BigUint::from(1_u8);
+        for index in (0..joltages.len() - 1).rev() {
+            let count = (index + 1..joltages.len())
+                .take_while(|next| joltages[*next] - joltages[index] <= max_gap)
+                .filter(|next| gaps.contains(&(joltages[*next] - joltages[index])))
+                .map(|next| &arrangement_counts[next])
+                .sum();
+            arrangement_counts[index] = count;
         }
+
+        Ok(Self {
+            joltages,
+            gaps,
+            arrangement_counts,
+        })
     }
 
-    diff_1 * (diff_3 + 1)
-}
+    /// Number of differences of each size, chaining all the adapters
+    fn difference_histogram(&self) -> anyhow::Result<BTreeMap<u64, usize>> {
+        let mut histogram = BTreeMap::new();
+        for pair in self.joltages.windows(2) {
+            let difference = pair[1] - pair[0];
+            anyhow::ensure!(
+                self.gaps.contains(&difference),
+                "Adapters with joltages {} and {} cannot be chained",
+                pair[0],
+                pair[1]
+            );
+            *histogram.entry(difference).or_default() += 1;
+        }
+        Ok(histogram)
+    }
 
-fn part02(sorted_adapers: &[usize]) -> usize {
-    struct CachedCountCombinations<'a> {
-        input: &'a [usize],
-        memoized_results: HashMap<usize, usize>,
+    /// Number of distinct arrangements connecting the outlet to the device
+    fn arrangement_count(&self) -> &BigUint {
+        &self.arrangement_counts[0]
     }
-    impl<'a> CachedCountCombinations<'a> {
-        fn new(input: &'a [usize]) -> Self {
-            Self {
-                input,
-                memoized_results: HashMap::with_capacity(input.len()),
-            }
+
+    /// Lazily lists the arrangements, as joltages of the used adapters
+    fn arrangements(&self) -> Arrangements<'_> {
+        Arrangements {
+            analyser: self,
+            stack: if self.arrangement_counts[0] == BigUint::default() {
+                vec![]
+            } else {
+                vec![(0, 1)]
+            },
         }
+    }
+
+    /// First index, not before `from`, reachable from `index` and leading to the device
+    fn next_index(&self, index: usize, from: usize) -> Option<usize> {
+        let max_gap = self.gaps.last().copied().unwrap_or(0);
+        (from..self.joltages.len())
+            .take_while(|next| self.joltages[*next] - self.joltages[index] <= max_gap)
+            .find(|next| {
+                self.gaps
+                    .contains(&(self.joltages[*next] - self.joltages[index]))
+                    && self.arrangement_counts[*next] != BigUint::default()
+            })
+    }
+}
+
+/// Depth first exploration of the arrangements. Only joltages leading to the device are
+/// visited, so every explored chain completes an arrangement
+#[derive(Debug)]
+struct Arrangements<'a> {
+    analyser: &'a ChainAnalyser,
+    /// Indexes of the current chain, with the first index to consider as next element
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let device = self.analyser.joltages.len() - 1;
+        loop {
+            let (index, from) = self.stack.last_mut()?;
+            if *index == device {
+                let arrangement = self.stack[1..self.stack.len() - 1]
+                    .iter()
+                    .map(|(index, _)| self.analyser.joltages[*index])
+                    .collect();
+                self.stack.pop();
+                return Some(arrangement);
+            }
 
-        fn call(&mut self, index: usize) -> usize {
-            if let Some(result) = self.memoized_results.get(&index) {
-                *result
-            } else if index >= self.input.len() {
-                0
+            if let Some(next) = self.analyser.next_index(*index, *from) {
+                *from = next + 1;
+                self.stack.push((next, next + 1));
             } else {
-                let result = if index == self.input.len() - 1 {
-                    1
-                } else {
-                    let mut result = 0;
-                    for next_index in index + 1..=index + 3 {
-                        if next_index < self.input.len() {
-                            match self.input[next_index] - self.input[index] {
-                                1 | 2 | 3 => {
-                                    result += self.call(next_index);
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                    result
-                };
-                self.memoized_results.insert(index, result);
-                result
+                self.stack.pop();
             }
         }
     }
+}
 
-    let mut count_combinations = CachedCountCombinations::new(sorted_adapers);
+fn part01(analyser: &ChainAnalyser) -> anyhow::Result<usize> {
+    let histogram = analyser.difference_histogram()?;
+    Ok(histogram.get(&1).unwrap_or(&0) * histogram.get(&3).unwrap_or(&0))
+}
 
-    for idx in (0..sorted_adapers.len()).rev() {
-        count_combinations.call(idx);
-    }
+fn part02(analyser: &ChainAnalyser) -> BigUint {
+    analyser.arrangement_count().clone()
+}
 
-    count_combinations
-        .input
+fn main() -> anyhow::Result<()> {
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let adapters: Vec<u64> = lines
         .iter()
         .enumerate()
-        .take(3)
-        .map(|(index, value)| {
-            if value <= &3 {
-                count_combinations.memoized_results[&index]
-            } else {
-                0
-            }
+        .map(|(line_no, line)| {
+            line.parse()
+                .map_err(|error| anyhow::anyhow!("Line {}: {} ({})", line_no + 1, line, error))
         })
-        .sum()
-}
+        .collect::<Result<_, _>>()?;
+    let analyser = ChainAnalyser::new(&adapters, &opts.gaps)?;
 
-fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let mut sorted_adapers: Vec<_> = lines.iter().map(|line| line.parse().unwrap()).collect();
-    sorted_adapers.sort_unstable();
+    if opts.histogram {
+        for (difference, count) in analyser.difference_histogram()? {
+            println!("Difference {}: {}", difference, count);
+        }
+    }
+    if let Some(limit) = opts.arrangements {
+        for arrangement in analyser.arrangements().take(limit.unwrap_or(usize::MAX)) {
+            let joltages: Vec<String> = arrangement.iter().map(ToString::to_string).collect();
+            println!("{}", joltages.join(","));
+        }
+    }
 
-    println!("Part 1: {}", part01(&sorted_adapers));
-    println!("Part 2: {}", part02(&sorted_adapers));
+    println!("Part 1: {}", part01(&analyser)?);
+    println!("Part 2: {}", part02(&analyser));
     Ok(())
 }
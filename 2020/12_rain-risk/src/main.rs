@@ -1,109 +1,148 @@
-use helpers::input_lines;
+use clap::{Args, ValueEnum};
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Navigation mode used by `--log` and `--svg` (and reported in addition to the parts)
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
+
+    /// Prints the position of the ship after every move
+    #[arg(long)]
+    log: bool,
+
+    /// Exports the track of the ship as SVG
+    #[arg(long)]
+    svg: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Mode {
+    /// Moves are applied to the ship (Part 1)
+    Direct,
+    /// Moves are applied to a waypoint around the ship (Part 2)
+    Waypoint,
+    /// As waypoint, but the waypoint moves in the frame of the ship (east is ahead, north is on the left)
+    /// and rotations turn the ship
+    RelativeWaypoint,
+}
+
 #[derive(Clone, Copy, Debug)]
 enum Move {
-    East(usize),
-    Forward(usize),
-    North(usize),
-    RotateLeft(usize),
-    RotateRight(usize),
-    South(usize),
-    West(usize),
+    East(i64),
+    Forward(i64),
+    North(i64),
+    RotateLeft(i64),
+    RotateRight(i64),
+    South(i64),
+    West(i64),
 }
 
 impl FromStr for Move {
-    type Err = ();
+    type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (first_character, number) = scan_fmt!(s, "{[NSEWFRL]}{}", char, usize).unwrap();
+        let (first_character, number) = scan_fmt!(s, "{[NSEWFRL]}{d}", char, i64)
+            .map_err(|_| anyhow::anyhow!("Invalid move: '{}'", s))?;
         match first_character {
             'E' => Ok(Self::East(number)),
             'F' => Ok(Self::Forward(number)),
-            'L' => {
-                assert!(number == 0 || number == 90 || number == 180 || number == 270);
-                Ok(Self::RotateLeft(number))
-            }
+            'L' => Ok(Self::RotateLeft(number)),
             'N' => Ok(Self::North(number)),
             'R' => Ok(Self::RotateRight(number)),
             'S' => Ok(Self::South(number)),
             'W' => Ok(Self::West(number)),
-            _ => unreachable!("Unknown rule: {}", first_character),
+            _ => Err(anyhow::anyhow!("Unknown rule: {}", first_character)),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-enum ShipDirection {
-    East,
-    North,
-    South,
-    West,
-}
-
-fn degree_to_quarters(degrees: i64) -> i64 {
-    // degrees > 0 => degrees counter-clockwise rotation
-    // degrees < 0 => -degrees counter-clockwise rotation
-    let mut quarters = -degrees / 90;
-    while quarters < 0 {
-        quarters += 4;
-    }
-    quarters
-}
-
-impl ShipDirection {
-    fn rotate(&mut self, degrees: i64) {
-        fn quarter(value: ShipDirection) -> i64 {
-            match value {
-                ShipDirection::East => 0,
-                ShipDirection::South => 1,
-                ShipDirection::West => 2,
-                ShipDirection::North => 3,
-            }
-        }
-
-        *self = match (quarter(*self) + degree_to_quarters(degrees)).checked_rem(4) {
-            Some(0) => Self::East,
-            Some(1) => Self::South,
-            Some(2) => Self::West,
-            Some(3) => Self::North,
-            unknown_value => unreachable!("Not possible value: {:?}", unknown_value),
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::East(value) => write!(f, "E{}", value),
+            Self::Forward(value) => write!(f, "F{}", value),
+            Self::North(value) => write!(f, "N{}", value),
+            Self::RotateLeft(value) => write!(f, "L{}", value),
+            Self::RotateRight(value) => write!(f, "R{}", value),
+            Self::South(value) => write!(f, "S{}", value),
+            Self::West(value) => write!(f, "W{}", value),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 struct Point {
     east: i64,
     north: i64,
 }
 
+impl std::ops::AddAssign for Point {
+    fn add_assign(&mut self, rhs: Self) {
+        self.east += rhs.east;
+        self.north += rhs.north;
+    }
+}
+
+impl std::ops::Mul<i64> for Point {
+    type Output = Self;
+    fn mul(self, rhs: i64) -> Self {
+        Self {
+            east: self.east * rhs,
+            north: self.north * rhs,
+        }
+    }
+}
+
 impl Point {
-    fn rotate(&mut self, degrees: i64) {
-        let rotation_in_quarters = degree_to_quarters(degrees);
-        match rotation_in_quarters {
-            0 => {
-                // (x, y) => (x, y)
-            }
-            1 => {
-                // (x, y) => (y, -x)
-                std::mem::swap(&mut self.east, &mut self.north);
-                self.east *= -1;
-            }
-            2 => {
-                // (x, y) => (-x, -y)
-                self.east *= -1;
-                self.north *= -1;
-            }
-            3 => {
-                // (x, y) => (-y, x)
-                std::mem::swap(&mut self.east, &mut self.north);
-                self.north *= -1;
+    /// Point moved by the cardinal moves, other moves are ignored
+    fn translate(self, move_: Move) -> Self {
+        let (east, north) = match move_ {
+            Move::East(value) => (value, 0),
+            Move::North(value) => (0, value),
+            Move::South(value) => (0, -value),
+            Move::West(value) => (-value, 0),
+            Move::Forward(_) | Move::RotateLeft(_) | Move::RotateRight(_) => (0, 0),
+        };
+        Self {
+            east: self.east + east,
+            north: self.north + north,
+        }
+    }
+
+    /// Rotation around the origin by `degrees` counter-clockwise.
+    ///
+    /// Multiples of 90 degrees are exact, other angles are rounded to the closest integer point.
+    fn rotate(self, degrees: i64) -> Self {
+        match degrees.rem_euclid(360) {
+            0 => self,
+            90 => Self {
+                east: -self.north,
+                north: self.east,
+            },
+            180 => Self {
+                east: -self.east,
+                north: -self.north,
+            },
+            270 => Self {
+                east: self.north,
+                north: -self.east,
+            },
+            degrees => {
+                let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+                let (east, north) = (self.east as f64, self.north as f64);
+                Self {
+                    east: (east * cos - north * sin).round() as i64,
+                    north: (east * sin + north * cos).round() as i64,
+                }
             }
-            unknown_value => unreachable!("Not possible value: {:?}", unknown_value),
         }
     }
 
@@ -112,124 +151,190 @@ impl Point {
     }
 }
 
-#[derive(Debug)]
-struct Ship {
-    direction: ShipDirection,
-    // moves: &'a [Move],
-    position: Point,
-    waypoint_position: Option<Point>,
+/// Counter-clockwise rotation (in degrees) described by the move, if it is a rotation
+fn rotation(move_: Move) -> Option<i64> {
+    match move_ {
+        Move::RotateLeft(value) => Some(value),
+        Move::RotateRight(value) => Some(-value),
+        _ => None,
+    }
 }
 
-impl Ship {
-    fn new() -> Self {
-        Self {
-            direction: ShipDirection::East,
-            position: Point { east: 0, north: 0 },
-            waypoint_position: None,
-        }
-    }
+/// Interpretation of the moves
+trait Navigator {
+    /// Applies the move, updating the position of the ship and the state of the navigator
+    fn perform_move(&mut self, position: &mut Point, move_: Move);
+}
 
-    fn new_with_waypoint(waypoint_position: Point) -> Self {
-        Self {
-            direction: ShipDirection::East,
-            position: Point { east: 0, north: 0 },
-            waypoint_position: Some(waypoint_position),
-        }
-    }
+/// Moves the ship itself, which is initially heading east
+#[derive(Debug, Default)]
+struct Direct {
+    /// Counter-clockwise degrees from east
+    heading: i64,
+}
 
-    fn perform_move_without_waypoint(&mut self, move_: &Move) {
-        match move_ {
-            Move::East(value) => {
-                self.position.east += *value as i64;
-            }
-            Move::Forward(value) => self.perform_move(&match self.direction {
-                ShipDirection::South => Move::South(*value),
-                ShipDirection::North => Move::North(*value),
-                ShipDirection::West => Move::West(*value),
-                ShipDirection::East => Move::East(*value),
-            }),
-            Move::North(value) => {
-                self.position.north += *value as i64;
-            }
-            Move::RotateLeft(value) => {
-                self.direction.rotate(*value as i64);
-            }
-            Move::RotateRight(value) => {
-                self.direction.rotate(-(*value as i64));
-            }
-            Move::South(value) => {
-                self.position.north -= *value as i64;
-            }
-            Move::West(value) => {
-                self.position.east -= *value as i64;
+impl Navigator for Direct {
+    fn perform_move(&mut self, position: &mut Point, move_: Move) {
+        if let Some(degrees) = rotation(move_) {
+            self.heading += degrees;
+        } else if let Move::Forward(value) = move_ {
+            *position += Point {
+                east: value,
+                north: 0,
             }
+            .rotate(self.heading);
+        } else {
+            *position = position.translate(move_);
         }
     }
+}
 
-    fn perform_move_with_waypoint(&mut self, move_: &Move) {
-        let waypoint_position: &mut Point = self.waypoint_position.as_mut().unwrap();
-        match move_ {
-            Move::East(value) => {
-                waypoint_position.east += *value as i64;
-            }
-            Move::Forward(value) => {
-                self.position.east += (*value as i64) * waypoint_position.east;
-                self.position.north += (*value as i64) * waypoint_position.north;
-            }
-            Move::North(value) => {
-                waypoint_position.north += *value as i64;
-            }
-            Move::RotateLeft(value) => {
-                waypoint_position.rotate(-(*value as i64));
-            }
-            Move::RotateRight(value) => {
-                waypoint_position.rotate(*value as i64);
-            }
-            Move::South(value) => {
-                waypoint_position.north -= *value as i64;
-            }
-            Move::West(value) => {
-                waypoint_position.east -= *value as i64;
-            }
+/// Moves a waypoint (relative to the ship) and the ship towards it
+#[derive(Debug)]
+struct Waypoint {
+    waypoint: Point,
+}
+
+impl Navigator for Waypoint {
+    fn perform_move(&mut self, position: &mut Point, move_: Move) {
+        if let Some(degrees) = rotation(move_) {
+            self.waypoint = self.waypoint.rotate(degrees);
+        } else if let Move::Forward(value) = move_ {
+            *position += self.waypoint * value;
+        } else {
+            self.waypoint = self.waypoint.translate(move_);
         }
     }
+}
+
+/// Moves a waypoint in the frame of the ship (east is ahead of the ship and north is on its left),
+/// while rotations turn the ship
+#[derive(Debug)]
+struct RelativeWaypoint {
+    waypoint: Point,
+    /// Counter-clockwise degrees from east
+    heading: i64,
+}
 
-    fn perform_move(&mut self, move_: &Move) {
-        if self.waypoint_position.is_none() {
-            self.perform_move_without_waypoint(move_);
+impl Navigator for RelativeWaypoint {
+    fn perform_move(&mut self, position: &mut Point, move_: Move) {
+        if let Some(degrees) = rotation(move_) {
+            self.heading += degrees;
+        } else if let Move::Forward(value) = move_ {
+            *position += self.waypoint.rotate(self.heading) * value;
         } else {
-            self.perform_move_with_waypoint(move_);
+            self.waypoint = self.waypoint.translate(move_);
         }
     }
+}
 
-    fn manhattan_distance(&self) -> u64 {
-        self.position.manhattan_distance()
+impl Mode {
+    fn navigator(self) -> Box<dyn Navigator> {
+        let waypoint = Point { east: 10, north: 1 };
+        match self {
+            Self::Direct => Box::new(Direct::default()),
+            Self::Waypoint => Box::new(Waypoint { waypoint }),
+            Self::RelativeWaypoint => Box::new(RelativeWaypoint {
+                waypoint,
+                heading: 0,
+            }),
+        }
     }
 }
 
-fn part01(moves: &[Move]) -> u64 {
-    let mut ship = Ship::new();
+/// Positions of the ship, starting from the origin and after every move
+fn journey(navigator: &mut dyn Navigator, moves: &[Move]) -> Vec<Point> {
+    let mut position = Point::default();
+    let mut track = vec![position];
     for move_ in moves {
-        ship.perform_move(move_);
+        navigator.perform_move(&mut position, *move_);
+        track.push(position);
     }
-    ship.manhattan_distance()
+    track
 }
 
-fn part02(moves: &[Move]) -> u64 {
-    let mut ship = Ship::new_with_waypoint(Point { east: 10, north: 1 });
-    for move_ in moves {
-        ship.perform_move(move_);
+fn write_svg(track: &[Point], path: &PathBuf) -> anyhow::Result<()> {
+    let min_east = track.iter().map(|point| point.east).min().unwrap_or(0);
+    let max_east = track.iter().map(|point| point.east).max().unwrap_or(0);
+    let min_north = track.iter().map(|point| point.north).min().unwrap_or(0);
+    let max_north = track.iter().map(|point| point.north).max().unwrap_or(0);
+    let margin = ((max_east - min_east).max(max_north - min_north) / 20).max(1);
+    let points: Vec<String> = track
+        .iter()
+        // SVG y axis points south
+        .map(|point| format!("{},{}", point.east, -point.north))
+        .collect();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        min_east - margin,
+        -max_north - margin,
+        max_east - min_east + 2 * margin,
+        max_north - min_north + 2 * margin
+    )?;
+    writeln!(
+        writer,
+        r#"  <polyline points="{}" fill="none" stroke="navy" stroke-width="{}"/>"#,
+        points.join(" "),
+        margin as f64 / 5.0
+    )?;
+    for (point, colour) in [(track.first(), "green"), (track.last(), "red")] {
+        if let Some(point) = point {
+            writeln!(
+                writer,
+                r#"  <circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                point.east,
+                -point.north,
+                margin as f64 / 2.0,
+                colour
+            )?;
+        }
     }
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
 
-    ship.manhattan_distance()
+fn final_distance(mode: Mode, moves: &[Move]) -> u64 {
+    journey(mode.navigator().as_mut(), moves)
+        .last()
+        .map_or(0, Point::manhattan_distance)
+}
+
+fn part01(moves: &[Move]) -> u64 {
+    final_distance(Mode::Direct, moves)
+}
+
+fn part02(moves: &[Move]) -> u64 {
+    final_distance(Mode::Waypoint, moves)
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let moves: Vec<_> = lines
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let moves: Vec<Move> = lines
         .iter()
-        .map(|line| line.parse::<Move>().unwrap())
-        .collect();
+        .map(|line| line.parse())
+        .collect::<Result<_, _>>()?;
+
+    let mode = opts.mode.unwrap_or(Mode::Direct);
+    let track = journey(mode.navigator().as_mut(), &moves);
+    if opts.log {
+        for (move_, point) in moves.iter().zip(&track[1..]) {
+            println!("{}: east {}, north {}", move_, point.east, point.north);
+        }
+    }
+    if let Some(path) = &opts.svg {
+        write_svg(&track, path)?;
+    }
+    if let Some(mode) = opts.mode {
+        println!(
+            "{:?}: {}",
+            mode,
+            track.last().map_or(0, Point::manhattan_distance)
+        );
+    }
+
     println!("Part 1: {}", part01(&moves));
     println!("Part 2: {}", part02(&moves));
     Ok(())
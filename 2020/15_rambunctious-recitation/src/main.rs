@@ -1,96 +1,121 @@
-use helpers::input_lines;
-use std::collections::{HashMap, VecDeque};
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::collections::HashMap;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Additional turns (1-based, comma separated) whose spoken number is reported
+    #[arg(long, value_delimiter = ',')]
+    turns: Vec<usize>,
+}
+
+/// Van Eck sequence seeded by the starting numbers. Iterating yields the number spoken on each turn.
+///
+/// Numbers smaller than the dense capacity track their last turn in a flat array,
+/// bigger numbers fall back on a map.
 #[derive(Debug)]
-struct CircularBuffer<T> {
-    values: VecDeque<T>,
-    capacity: usize,
+struct VanEck {
+    starting_numbers: Vec<u32>,
+    /// 1-based turn in which each number was last spoken (0 if never),
+    /// the number spoken in the last turn is not yet registered
+    last_seen: Vec<u32>,
+    sparse_last_seen: HashMap<u32, u32>,
+    turn: u32,
+    last_spoken: Option<u32>,
 }
 
-impl<T: Eq> CircularBuffer<T> {
-    fn new(capacity: usize) -> Self {
+impl VanEck {
+    fn with_capacity(starting_numbers: &[u32], dense_capacity: usize) -> Self {
         Self {
-            values: VecDeque::new(),
-            capacity,
+            starting_numbers: starting_numbers.to_vec(),
+            last_seen: vec![0; dense_capacity],
+            sparse_last_seen: HashMap::new(),
+            turn: 0,
+            last_spoken: None,
         }
     }
 
-    fn add(&mut self, value: T) {
-        if self.values.len() >= self.capacity {
-            self.values.pop_front();
+    /// Registers the turn of `number`, returning the turn it was previously spoken in (0 if never)
+    fn replace_last_seen(&mut self, number: u32, turn: u32) -> u32 {
+        match self.last_seen.get_mut(number as usize) {
+            Some(last_seen) => std::mem::replace(last_seen, turn),
+            None => self.sparse_last_seen.insert(number, turn).unwrap_or(0),
         }
-        self.values.push_back(value);
-    }
-
-    fn last(&self) -> Option<&T> {
-        self.values.back()
     }
+}
 
-    fn first(&self) -> Option<&T> {
-        self.values.front()
-    }
+impl Iterator for VanEck {
+    type Item = u32;
 
-    fn len(&self) -> usize {
-        self.values.len()
+    fn next(&mut self) -> Option<Self::Item> {
+        let previous_turn = self.last_spoken.map_or(0, |last_spoken| {
+            self.replace_last_seen(last_spoken, self.turn)
+        });
+        let spoken = match self.starting_numbers.get(self.turn as usize) {
+            Some(starting_number) => *starting_number,
+            None if previous_turn == 0 => 0,
+            None => self.turn - previous_turn,
+        };
+        self.turn = self.turn.checked_add(1)?;
+        self.last_spoken = Some(spoken);
+        Some(spoken)
     }
 }
 
-fn game(input: &[usize], turns: usize) -> usize {
-    if turns < input.len() {
-        input[turns]
-    } else {
-        let mut values: Vec<usize> = vec![0; turns];
-        let mut value_to_turns: HashMap<usize, CircularBuffer<usize>> = HashMap::new();
-
-        macro_rules! play {
-            ($turn:expr, $value: expr) => {
-                values[$turn] = $value;
-                value_to_turns
-                    .entry($value)
-                    .or_insert_with(|| CircularBuffer::new(2))
-                    .add($turn);
-            };
-        }
+/// Numbers spoken in the given (1-based) turns, computed in a single run of the game
+fn spoken_at(starting_numbers: &[u32], turns: &[usize]) -> anyhow::Result<Vec<u32>> {
+    anyhow::ensure!(!starting_numbers.is_empty(), "No starting numbers");
+    anyhow::ensure!(
+        turns.iter().all(|turn| *turn > 0),
+        "Turns are expected to start from 1"
+    );
+    let last_turn = turns.iter().copied().max().unwrap_or(0);
 
-        for (turn, value) in input.iter().enumerate() {
-            play!(turn, *value);
-        }
+    let mut sorted_turns: Vec<usize> = turns.to_vec();
+    sorted_turns.sort_unstable();
+    sorted_turns.dedup();
 
-        for turn in input.len()..turns {
-            let last = values[(turn - 1) as usize];
-            match value_to_turns.get(&last) {
-                Some(turns) if turns.len() > 1 => {
-                    let last_turn = *turns.last().unwrap();
-                    let first_turn = *turns.first().unwrap();
-                    play!(turn, last_turn - first_turn);
-                }
-                _ => {
-                    play!(turn, 0);
-                }
-            }
+    let mut spoken_by_turn = HashMap::new();
+    let mut pending_turns = sorted_turns.iter().peekable();
+    for (turn, spoken) in VanEck::with_capacity(starting_numbers, last_turn)
+        .enumerate()
+        .take(last_turn)
+    {
+        if pending_turns.next_if_eq(&&(turn + 1)).is_some() {
+            spoken_by_turn.insert(turn + 1, spoken);
         }
-        *values.last().unwrap()
     }
-}
 
-fn part01(input: &[usize]) -> usize {
-    game(input, 2_020)
-}
-
-fn part02(input: &[usize]) -> usize {
-    game(input, 30_000_000)
+    turns
+        .iter()
+        .map(|turn| {
+            spoken_by_turn
+                .get(turn)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Turn {} is beyond the end of the game", turn))
+        })
+        .collect()
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let input: Vec<usize> = lines[0]
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input: Vec<u32> = lines[0]
         .split(',')
         .map(str::parse)
         .collect::<Result<_, _>>()?;
 
-    println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    let turns: Vec<usize> = [2_020, 30_000_000]
+        .into_iter()
+        .chain(opts.turns.iter().copied())
+        .collect();
+    let spoken = spoken_at(&input, &turns)?;
+
+    println!("Part 1: {}", spoken[0]);
+    println!("Part 2: {}", spoken[1]);
+    for (turn, spoken) in turns.iter().zip(&spoken).skip(2) {
+        println!("Turn {}: {}", turn, spoken);
+    }
     Ok(())
 }
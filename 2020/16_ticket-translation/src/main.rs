@@ -1,63 +1,46 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::matching::BipartiteGraph;
 use scan_fmt::scan_fmt;
-use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::RangeInclusive;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the candidate fields of each column and the resolved assignment
+    #[arg(long)]
+    explain: bool,
+}
+
+#[derive(Debug)]
+struct Field<'a> {
+    name: &'a str,
+    ranges: Vec<RangeInclusive<usize>>,
+}
+
+impl Field<'_> {
+    fn accepts(&self, value: usize) -> bool {
+        self.ranges.iter().any(|range| range.contains(&value))
+    }
+}
+
 #[derive(Debug)]
 struct Ticket {
     field_values: Vec<usize>,
 }
 
 impl Ticket {
-    fn invalid_field_values(
-        &self,
-        field_to_ranges: &HashMap<&str, Vec<RangeInclusive<usize>>>,
-    ) -> Vec<usize> {
+    fn invalid_field_values(&self, fields: &[Field]) -> Vec<usize> {
         self.field_values
             .iter()
-            .filter_map(|field| {
-                if field_to_ranges
-                    .values()
-                    .any(|ranges| ranges.iter().any(|range| range.contains(field)))
-                {
-                    None
-                } else {
-                    Some(*field)
-                }
-            })
+            .copied()
+            .filter(|value| !fields.iter().any(|field| field.accepts(*value)))
             .collect()
     }
 
-    fn is_valid(&self, field_to_ranges: &HashMap<&str, Vec<RangeInclusive<usize>>>) -> bool {
-        self.field_values.iter().all(|field| {
-            field_to_ranges
-                .values()
-                .any(|ranges| ranges.iter().any(|range| range.contains(field)))
-        })
-    }
-
-    fn possible_columns_for_fields<'a>(
-        &self,
-        field_to_ranges: &HashMap<&'a str, Vec<RangeInclusive<usize>>>,
-    ) -> Option<Vec<HashSet<&'a str>>> {
-        if self.is_valid(field_to_ranges) {
-            // Ensure that we operate only on valid rows
-            let mut field_to_possible_columns: Vec<HashSet<&'a str>> =
-                vec![HashSet::new(); self.field_values.len()];
-            for (index, field_value) in self.field_values.iter().enumerate() {
-                for (field_name, ranges) in field_to_ranges.iter() {
-                    if ranges.iter().any(|range| range.contains(field_value)) {
-                        field_to_possible_columns[index].insert(field_name);
-                    }
-                }
-            }
-            Some(field_to_possible_columns)
-        } else {
-            // The row is not valid
-            None
-        }
+    fn is_valid(&self, fields: &[Field]) -> bool {
+        self.invalid_field_values(fields).is_empty()
     }
 }
 
@@ -74,7 +57,7 @@ impl From<&String> for Ticket {
 
 #[derive(Debug)]
 struct Input<'a> {
-    field_to_ranges: HashMap<&'a str, Vec<RangeInclusive<usize>>>,
+    fields: Vec<Field<'a>>,
     nearby_tickets: Vec<Ticket>,
     ticket: Ticket,
 }
@@ -83,12 +66,11 @@ impl<'a> From<&'a Vec<String>> for Input<'a> {
     fn from(lines: &'a Vec<String>) -> Self {
         let mut lines_iter = lines.iter();
 
-        let field_to_ranges = lines_iter
+        let fields = lines_iter
             .by_ref()
             .take_while(|line| !line.is_empty())
             .map(|line| {
                 let line_parts: Vec<_> = line.split(": ").collect();
-                let field = line_parts[0];
                 let ranges = line_parts[1]
                     .split(" or ")
                     .map(|range| {
@@ -96,7 +78,10 @@ impl<'a> From<&'a Vec<String>> for Input<'a> {
                         RangeInclusive::new(start, end)
                     })
                     .collect();
-                (field, ranges)
+                Field {
+                    name: line_parts[0],
+                    ranges,
+                }
             })
             .collect();
 
@@ -118,110 +103,121 @@ impl<'a> From<&'a Vec<String>> for Input<'a> {
             .collect();
 
         Self {
-            field_to_ranges,
+            fields,
             nearby_tickets,
             ticket,
         }
     }
 }
 
-fn part01(input: &Input) -> usize {
-    input
+/// Fields (as indexes of `input.fields`) accepting the values of each column in all the valid nearby tickets
+fn candidate_fields_per_column(input: &Input) -> Vec<Vec<usize>> {
+    let valid_tickets: Vec<&Ticket> = input
         .nearby_tickets
         .iter()
-        .map(|ticket| {
-            ticket
-                .invalid_field_values(&input.field_to_ranges)
-                .iter()
-                .sum::<usize>()
+        .filter(|ticket| ticket.is_valid(&input.fields))
+        .collect();
+
+    (0..input.ticket.field_values.len())
+        .map(|column| {
+            (0..input.fields.len())
+                .filter(|field| {
+                    valid_tickets.iter().all(|ticket| {
+                        ticket
+                            .field_values
+                            .get(column)
+                            .is_some_and(|value| input.fields[*field].accepts(*value))
+                    })
+                })
+                .collect()
         })
-        .sum()
+        .collect()
 }
 
-fn part02(input: &Input) -> usize {
-    fn update_possible_fields_per_column<'a>(
-        ticket: &Ticket,
-        input: &Input,
-        possible_fields_per_column: &mut BTreeMap<usize, HashSet<&'a str>>,
-        certanly_allocated_field_names: &mut HashSet<&'a str>,
-    ) {
-        if let Some(possible_columns_for_fields) =
-            ticket.possible_columns_for_fields(&input.field_to_ranges)
-        {
-            for (general_possible_fields, possible_field_for_ticket) in possible_fields_per_column
-                .values_mut()
-                .zip(possible_columns_for_fields.iter())
-            {
-                general_possible_fields.retain(|value| possible_field_for_ticket.contains(value));
-                if general_possible_fields.len() == 1 {
-                    certanly_allocated_field_names
-                        .insert(*general_possible_fields.iter().next().unwrap());
-                }
-            }
-
-            loop {
-                let mut should_break = true;
-                for general_possible_fields in possible_fields_per_column.values_mut() {
-                    if general_possible_fields.len() > 1 {
-                        general_possible_fields
-                            .retain(|value| !certanly_allocated_field_names.contains(value));
-                        if general_possible_fields.len() == 1 {
-                            // As removing the other certain fields we have only one
-                            // left, then it is certain as well
-                            certanly_allocated_field_names
-                                .insert(general_possible_fields.iter().next().unwrap());
-                            // Ensure that we try to cleanup the general_possible_fields again
-                            should_break = false;
-                        }
-                    }
-                }
-                if should_break {
-                    break;
-                }
-            }
+fn field_names(input: &Input, fields: impl IntoIterator<Item = usize>) -> String {
+    fields
+        .into_iter()
+        .map(|field| input.fields[field].name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Assigns a field to each column of the tickets.
+///
+/// Fails if no assignment is possible or if multiple assignments are possible.
+fn assign_fields<'a>(input: &Input<'a>, explain: bool) -> anyhow::Result<Vec<&'a str>> {
+    let graph = BipartiteGraph::new(candidate_fields_per_column(input), input.fields.len());
+    if explain {
+        for column in 0..graph.left_count() {
+            let fields = graph.neighbours(column);
+            println!(
+                "Column {} candidates: {}",
+                column,
+                field_names(input, fields.iter().copied())
+            );
         }
     }
 
-    let mut possible_fields_per_column = (0..input.ticket.field_values.len())
-        .map(|index| (index, input.field_to_ranges.keys().copied().collect()))
-        .collect::<BTreeMap<_, HashSet<_>>>();
-
-    let mut certanly_allocated_field_names = HashSet::new();
+    let matching = graph.maximum_matching();
+    if let Some(unmatched_column) = matching.unmatched_left() {
+        let (columns, fields) = graph.hall_violator(&matching, unmatched_column);
+        return Err(anyhow::anyhow!(
+            "No valid field assignment: the {} columns {:?} accept only the {} fields [{}]",
+            columns.len(),
+            columns.into_iter().collect::<Vec<_>>(),
+            fields.len(),
+            field_names(input, fields)
+        ));
+    }
 
-    for nearby_ticket_ in &input.nearby_tickets {
-        let nearby_ticket: &Ticket = nearby_ticket_;
-        update_possible_fields_per_column(
-            nearby_ticket,
-            input,
-            &mut possible_fields_per_column,
-            &mut certanly_allocated_field_names,
-        );
+    for column in 0..graph.left_count() {
+        if let Some(alternative) = graph.alternative_matching(&matching, column) {
+            return Err(anyhow::anyhow!(
+                "Ambiguous field assignment: column {} can hold either {} or {}",
+                column,
+                field_names(input, matching.right_of(column)),
+                field_names(input, alternative.right_of(column))
+            ));
+        }
     }
 
-    // Ensure that we have found info for all the fields
-    assert_eq!(
-        certanly_allocated_field_names.len(),
-        input.ticket.field_values.len()
-    );
+    let assignment: Vec<&str> = (0..graph.left_count())
+        .map(|column| input.fields[matching.right_of(column).unwrap_or_default()].name)
+        .collect();
+    if explain {
+        for (column, field_name) in assignment.iter().enumerate() {
+            println!("Column {} is {}", column, field_name);
+        }
+    }
+    Ok(assignment)
+}
 
-    possible_fields_per_column
+fn part01(input: &Input) -> usize {
+    input
+        .nearby_tickets
         .iter()
-        .map(|(index, field_names)| {
-            let field_name = field_names.iter().next().unwrap();
-            if field_name.starts_with("departure") {
-                input.ticket.field_values[*index]
-            } else {
-                // Neutral value for a multiplication
-                1
-            }
+        .map(|ticket| {
+            ticket
+                .invalid_field_values(&input.fields)
+                .iter()
+                .sum::<usize>()
         })
-        .product()
+        .sum()
+}
+
+fn part02(input: &Input, explain: bool) -> anyhow::Result<usize> {
+    Ok(assign_fields(input, explain)?
+        .iter()
+        .zip(&input.ticket.field_values)
+        .filter(|(field_name, _)| field_name.starts_with("departure"))
+        .map(|(_, value)| value)
+        .product())
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
     let input = Input::from(&lines);
     println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    println!("Part 2: {}", part02(&input, opts.explain)?);
     Ok(())
 }
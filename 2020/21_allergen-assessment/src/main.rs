@@ -1,101 +1,223 @@
-use helpers::input_lines;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::matching::BipartiteGraph;
+use std::collections::{BTreeMap, BTreeSet};
 
 const INPUT: &str = include_str!("../input.txt");
 
-#[derive(Clone, Debug)]
-struct Input<'a> {
-    allergen_to_ingredients: HashMap<&'a str, HashSet<&'a str>>,
-    ingredients: Vec<&'a str>,
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints, for each allergen, the foods that constrained it and the resolved ingredient
+    #[arg(long)]
+    explain: bool,
 }
 
-impl<'a> From<&Vec<&'a str>> for Input<'a> {
-    fn from(lines: &Vec<&'a str>) -> Self {
-        let mut allergen_to_ingredients: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
-        let mut all_ingredients = Vec::new();
-
-        for line in lines {
-            let (column_index, _) = line
-                .as_bytes()
-                .iter()
-                .enumerate()
-                .find(|(_, character)| *character == &b'(')
-                .unwrap();
-            let current_ingredients: HashSet<_> = line[..column_index]
-                .split(' ')
-                .filter(|ingredient| !ingredient.is_empty())
-                .collect();
-
-            line[column_index..]
-                .strip_prefix("(contains ")
-                .and_then(|s| s.strip_suffix(')'))
-                .map(|s| s.split(", "))
-                .unwrap()
-                .for_each(|allergen| {
-                    allergen_to_ingredients
-                        .entry(allergen)
-                        .and_modify(|ingredients: &mut HashSet<&'a str>| {
-                            ingredients.retain(|value| current_ingredients.contains(value));
-                        })
-                        .or_insert_with(|| current_ingredients.clone());
-                });
-            all_ingredients.extend(current_ingredients.iter());
-        }
+#[derive(Debug)]
+struct Food<'a> {
+    ingredients: BTreeSet<&'a str>,
+    /// Allergens known to be present. Allergens are not always marked
+    allergens: Vec<&'a str>,
+}
 
-        Self {
-            allergen_to_ingredients,
-            ingredients: all_ingredients,
-        }
+impl<'a> TryFrom<&'a str> for Food<'a> {
+    type Error = anyhow::Error;
+
+    fn try_from(line: &'a str) -> Result<Self, Self::Error> {
+        let (ingredients, allergens) = match line.split_once('(') {
+            Some((ingredients, allergens)) => {
+                let allergens = allergens
+                    .strip_prefix("contains ")
+                    .ok_or_else(|| anyhow::anyhow!("Expected `(contains ` in '{}'", line))?
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow::anyhow!("Expected `)` at the end of '{}'", line))?;
+                let allergens: Vec<&str> = allergens.split(", ").map(str::trim).collect();
+                anyhow::ensure!(
+                    allergens.iter().all(|allergen| !allergen.is_empty()),
+                    "Empty allergen in '{}'",
+                    line
+                );
+                (ingredients, allergens)
+            }
+            None => (line, vec![]),
+        };
+
+        let ingredients: BTreeSet<&str> = ingredients.split_whitespace().collect();
+        anyhow::ensure!(!ingredients.is_empty(), "No ingredients in '{}'", line);
+        Ok(Self {
+            ingredients,
+            allergens,
+        })
     }
 }
 
-fn part01(input: &Input) -> usize {
-    let ingredients_with_allergene: HashSet<_> =
-        input.allergen_to_ingredients.values().flatten().collect();
-
-    let ingredients_with_no_allergene_count = input
-        .ingredients
-        .iter()
-        .filter(|ingredient| !ingredients_with_allergene.contains(ingredient))
-        .count();
-
-    ingredients_with_no_allergene_count
+#[derive(Debug)]
+struct AllergenCandidates<'a> {
+    /// Ingredients present in all the foods containing the allergen
+    ingredients: BTreeSet<&'a str>,
+    /// Indexes of the foods containing the allergen
+    foods: Vec<usize>,
 }
 
-fn part02(input: &Input) -> String {
-    let mut cloned_input = input.clone();
+#[derive(Debug)]
+struct Input<'a> {
+    foods: Vec<Food<'a>>,
+    allergen_candidates: BTreeMap<&'a str, AllergenCandidates<'a>>,
+}
 
-    // BTreeMap to avoid explicit sorting <- as it does not change complexity
-    let mut allergen_to_ingredient: BTreeMap<&str, &str> = BTreeMap::new();
-    let mut used_ingredients: HashSet<&str> = HashSet::new();
+impl<'a> TryFrom<&'a [String]> for Input<'a> {
+    type Error = anyhow::Error;
+
+    fn try_from(lines: &'a [String]) -> Result<Self, Self::Error> {
+        let foods = lines
+            .iter()
+            .enumerate()
+            .map(|(line_no, line)| {
+                Food::try_from(line.as_str())
+                    .map_err(|error| anyhow::anyhow!("Line {}: {}", line_no + 1, error))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut allergen_candidates: BTreeMap<&str, AllergenCandidates> = BTreeMap::new();
+        for (food_index, food) in foods.iter().enumerate() {
+            for allergen in &food.allergens {
+                let candidates =
+                    allergen_candidates
+                        .entry(allergen)
+                        .or_insert_with(|| AllergenCandidates {
+                            ingredients: food.ingredients.clone(),
+                            foods: vec![],
+                        });
+                candidates
+                    .ingredients
+                    .retain(|ingredient| food.ingredients.contains(ingredient));
+                candidates.foods.push(food_index);
+            }
+        }
 
-    while allergen_to_ingredient.len() != cloned_input.allergen_to_ingredients.len() {
-        for (allergen, ingredients) in &cloned_input.allergen_to_ingredients {
-            if ingredients.len() == 1 && !allergen_to_ingredient.contains_key(allergen) {
-                used_ingredients.extend(ingredients.iter());
+        Ok(Self {
+            foods,
+            allergen_candidates,
+        })
+    }
+}
 
-                allergen_to_ingredient.insert(allergen, ingredients.iter().next().unwrap());
+impl<'a> Input<'a> {
+    /// Associates each allergen to the only ingredient that can contain it.
+    ///
+    /// Fails if no association is possible or if multiple associations are possible.
+    fn resolve(&self) -> anyhow::Result<BTreeMap<&'a str, &'a str>> {
+        let allergens: Vec<&str> = self.allergen_candidates.keys().copied().collect();
+        let ingredients: Vec<&str> = self
+            .allergen_candidates
+            .values()
+            .flat_map(|candidates| candidates.ingredients.iter().copied())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let graph = BipartiteGraph::new(
+            self.allergen_candidates
+                .values()
+                .map(|candidates| {
+                    candidates
+                        .ingredients
+                        .iter()
+                        .filter_map(|ingredient| ingredients.binary_search(ingredient).ok())
+                        .collect()
+                })
+                .collect(),
+            ingredients.len(),
+        );
+        let names = |indexes: BTreeSet<usize>, names: &[&str]| {
+            indexes
+                .into_iter()
+                .map(|index| names[index])
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let matching = graph.maximum_matching();
+        if let Some(unmatched_allergen) = matching.unmatched_left() {
+            let (conflicting_allergens, candidate_ingredients) =
+                graph.hall_violator(&matching, unmatched_allergen);
+            anyhow::bail!(
+                "Impossible input: the allergens [{}] can only be in the ingredients [{}]",
+                names(conflicting_allergens, &allergens),
+                names(candidate_ingredients, &ingredients)
+            );
+        }
+        for (allergen_index, allergen) in allergens.iter().enumerate() {
+            if let Some(alternative) = graph.alternative_matching(&matching, allergen_index) {
+                anyhow::bail!(
+                    "Ambiguous input: {} can be in {} or in {}",
+                    allergen,
+                    ingredients[matching.right_of(allergen_index).unwrap_or_default()],
+                    ingredients[alternative.right_of(allergen_index).unwrap_or_default()]
+                );
             }
         }
 
-        for ingredients in &mut cloned_input.allergen_to_ingredients.values_mut() {
-            ingredients.retain(|ingredient| !used_ingredients.contains(ingredient));
+        Ok(allergens
+            .iter()
+            .enumerate()
+            .map(|(allergen_index, allergen)| {
+                (
+                    *allergen,
+                    ingredients[matching.right_of(allergen_index).unwrap_or_default()],
+                )
+            })
+            .collect())
+    }
+
+    fn explain(&self, resolved: &BTreeMap<&str, &str>) {
+        for (allergen, candidates) in &self.allergen_candidates {
+            let foods: Vec<String> = candidates
+                .foods
+                .iter()
+                .map(|food_index| (food_index + 1).to_string())
+                .collect();
+            let ingredients: Vec<&str> = candidates.ingredients.iter().copied().collect();
+            println!(
+                "{}: foods {} leave [{}] => {}",
+                allergen,
+                foods.join(", "),
+                ingredients.join(", "),
+                resolved.get(allergen).unwrap_or(&"?")
+            );
         }
     }
+}
 
-    allergen_to_ingredient
+fn part01(input: &Input) -> usize {
+    let ingredients_with_allergene: BTreeSet<_> = input
+        .allergen_candidates
         .values()
-        .copied()
-        .collect::<Vec<_>>()
-        .join(",")
+        .flat_map(|candidates| candidates.ingredients.iter())
+        .collect();
+
+    input
+        .foods
+        .iter()
+        .flat_map(|food| food.ingredients.iter())
+        .filter(|ingredient| !ingredients_with_allergene.contains(ingredient))
+        .count()
+}
+
+fn part02(resolved: &BTreeMap<&str, &str>) -> String {
+    // Sorted by allergen, as the map is sorted
+    resolved.values().copied().collect::<Vec<_>>().join(",")
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let input = Input::from(&lines.iter().map(String::as_str).collect());
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input = Input::try_from(lines.as_slice())?;
+    let resolved = input.resolve();
+
+    if opts.explain {
+        input.explain(resolved.as_ref().unwrap_or(&BTreeMap::new()));
+    }
 
     println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    println!("Part 2: {}", part02(&resolved?));
 
     Ok(())
 }
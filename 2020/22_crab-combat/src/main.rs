@@ -1,209 +1,253 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
 
 const INPUT: &str = include_str!("../input.txt");
 
-#[derive(Clone, Debug, PartialEq)]
-enum Player {
-    Player1,
-    Player2,
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints every round and sub-game, as in the assignment examples
+    #[arg(long)]
+    log: bool,
 }
 
-trait Game {
-    fn deck(&self, player: Player) -> &VecDeque<usize>;
-
-    fn play(&mut self) -> (Player, usize);
+type Deck = VecDeque<usize>;
+
+fn format_player_deck(player: usize, deck: &Deck) -> String {
+    let cards = deck
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("Player {}'s deck: {}", player + 1, cards)
+        .trim_end()
+        .to_string()
+}
 
-    fn score(&self, player: Player) -> usize {
-        self.deck(player)
-            .iter()
-            .rev()
-            .enumerate()
-            .map(|(index, value)| value * (index + 1))
-            .sum()
-    }
+/// Rules defining when a round has to be resolved by a sub-game.
+trait RecursionRule {
+    /// Returns the number of cards each player brings into a sub-game, or `None` if the round
+    /// is won by the highest card.
+    ///
+    /// `cards` contains the card drawn by each player (`None` for players that are out of the game)
+    /// and `decks` the cards remaining after the draw.
+    fn sub_game_sizes(&self, cards: &[Option<usize>], decks: &[Deck]) -> Option<Vec<usize>>;
 
-    fn winner(&self) -> Option<Player> {
-        if self.deck(Player::Player1).is_empty() {
-            Some(Player::Player2)
-        } else if self.deck(Player::Player2).is_empty() {
-            Some(Player::Player1)
-        } else {
-            None
-        }
-    }
+    fn allows_sub_games(&self) -> bool;
 }
 
-#[derive(Debug)]
-struct CrabCombat {
-    player1: VecDeque<usize>,
-    player2: VecDeque<usize>,
-}
+/// Classic Combat: the highest card always wins the round
+struct CrabCombat;
+impl RecursionRule for CrabCombat {
+    fn sub_game_sizes(&self, _cards: &[Option<usize>], _decks: &[Deck]) -> Option<Vec<usize>> {
+        None
+    }
 
-impl From<&[String]> for CrabCombat {
-    fn from(lines: &[String]) -> Self {
-        let mut lines_iter = lines.iter();
-
-        let player1 = lines_iter
-            .by_ref()
-            .take_while(|line| !line.is_empty())
-            .skip(1)
-            .filter_map(|line| line.parse::<usize>().ok())
-            .collect();
-
-        let player2 = lines_iter
-            .by_ref()
-            .take_while(|line| !line.is_empty())
-            .skip(1)
-            .filter_map(|line| line.parse::<usize>().ok())
-            .collect();
-
-        Self::new(player1, player2)
+    fn allows_sub_games(&self) -> bool {
+        false
     }
 }
 
-impl Game for CrabCombat {
-    fn deck(&self, player: Player) -> &VecDeque<usize> {
-        match player {
-            Player::Player1 => &self.player1,
-            Player::Player2 => &self.player2,
+/// Recursive Combat: a sub-game is played if every player has at least as many cards
+/// as the value of the card they just drew
+struct RecursiveCombat;
+impl RecursionRule for RecursiveCombat {
+    fn sub_game_sizes(&self, cards: &[Option<usize>], decks: &[Deck]) -> Option<Vec<usize>> {
+        if cards
+            .iter()
+            .zip(decks)
+            .all(|(card, deck)| card.is_none_or(|card| card <= deck.len()))
+        {
+            Some(cards.iter().map(|card| card.unwrap_or(0)).collect())
+        } else {
+            None
         }
     }
 
-    fn play(&mut self) -> (Player, usize) {
-        while self.winner().is_none() {
-            let card1 = self.player1.pop_front().unwrap();
-            let card2 = self.player2.pop_front().unwrap();
-
-            if card1 > card2 {
-                self.player1.push_back(card1);
-                self.player1.push_back(card2);
-            } else {
-                self.player2.push_back(card2);
-                self.player2.push_back(card1);
-            }
-        }
-
-        let winner = self.winner().unwrap();
-        (winner.clone(), self.score(winner))
+    fn allows_sub_games(&self) -> bool {
+        true
     }
 }
 
-impl CrabCombat {
-    fn new(deck1: VecDeque<usize>, deck2: VecDeque<usize>) -> Self {
+/// Game engine supporting any number of players.
+///
+/// Each round all the players still in the game draw their top card. The winner of the round
+/// collects its own card first, followed by the other cards in decreasing order of value.
+/// A player is out of the game once its deck is empty and the game ends when only one player is left.
+/// If a round starts with the same decks of a previous round of the same game, the first player
+/// still in the game wins.
+struct Game<R: RecursionRule> {
+    rules: R,
+    log: bool,
+    games_count: usize,
+}
+
+impl<R: RecursionRule> Game<R> {
+    fn new(rules: R, log: bool) -> Self {
         Self {
-            player1: deck1,
-            player2: deck2,
+            rules,
+            log,
+            games_count: 0,
         }
     }
-}
 
-#[derive(Debug)]
-struct RecursiveCombat {
-    player1: VecDeque<usize>,
-    player2: VecDeque<usize>,
-    rounds_decks: HashSet<(VecDeque<usize>, VecDeque<usize>)>,
-}
-
-impl From<&[String]> for RecursiveCombat {
-    fn from(lines: &[String]) -> Self {
-        let mut lines_iter = lines.iter();
-
-        let player1 = lines_iter
-            .by_ref()
-            .take_while(|line| !line.is_empty())
-            .skip(1)
-            .filter_map(|line| line.parse::<usize>().ok())
-            .collect();
-
-        let player2 = lines_iter
-            .by_ref()
-            .take_while(|line| !line.is_empty())
-            .skip(1)
-            .filter_map(|line| line.parse::<usize>().ok())
-            .collect();
-
-        Self::new_from_deque(player1, player2)
+    fn decks_hash(decks: &[Deck]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        decks.hash(&mut hasher);
+        hasher.finish()
     }
-}
 
-impl Game for RecursiveCombat {
-    fn deck(&self, player: Player) -> &VecDeque<usize> {
-        match player {
-            Player::Player1 => &self.player1,
-            Player::Player2 => &self.player2,
+    /// Plays a game returning the winning player and the final decks
+    fn play(&mut self, mut decks: Vec<Deck>) -> (usize, Vec<Deck>) {
+        self.games_count += 1;
+        let game_id = self.games_count;
+        let recursive = self.rules.allows_sub_games();
+        if self.log && recursive {
+            println!("=== Game {} ===", game_id);
         }
-    }
 
-    fn play(&mut self) -> (Player, usize) {
-        while self.winner().is_none() {
-            if self.rounds_decks.insert((
-                self.player1.iter().copied().collect(),
-                self.player2.iter().copied().collect(),
-            )) {
-                let card1 = self.player1.pop_front().unwrap();
-                let card2 = self.player2.pop_front().unwrap();
-
-                let round_winner = if card1 <= self.player1.len() && card2 <= self.player2.len() {
-                    let (sub_game_winner, _) = Self::new(
-                        self.player1.iter().take(card1).copied(),
-                        self.player2.iter().take(card2).copied(),
-                    )
-                    .play();
-                    sub_game_winner
-                } else if card1 > card2 {
-                    Player::Player1
-                } else {
-                    Player::Player2
-                };
+        let mut seen_decks: HashSet<u64> = HashSet::new();
+        let mut round = 0;
+        let winner = loop {
+            let active_players: Vec<usize> = (0..decks.len())
+                .filter(|player| !decks[*player].is_empty())
+                .collect();
+            if active_players.len() <= 1 {
+                break active_players.first().copied().unwrap_or(0);
+            }
+            if !seen_decks.insert(Self::decks_hash(&decks)) {
+                break active_players[0];
+            }
 
-                match round_winner {
-                    Player::Player1 => {
-                        self.player1.push_back(card1);
-                        self.player1.push_back(card2);
+            round += 1;
+            if self.log {
+                if recursive {
+                    println!("\n-- Round {} (Game {}) --", round, game_id);
+                } else {
+                    if round > 1 {
+                        println!();
                     }
-                    Player::Player2 => {
-                        self.player2.push_back(card2);
-                        self.player2.push_back(card1);
+                    println!("-- Round {} --", round);
+                }
+                for player in &active_players {
+                    println!("{}", format_player_deck(*player, &decks[*player]));
+                }
+            }
+
+            let cards: Vec<Option<usize>> = decks.iter_mut().map(VecDeque::pop_front).collect();
+            if self.log {
+                for (player, card) in cards.iter().enumerate() {
+                    if let Some(card) = card {
+                        println!("Player {} plays: {}", player + 1, card);
                     }
                 }
+            }
+
+            let round_winner = if let Some(sizes) = self.rules.sub_game_sizes(&cards, &decks) {
+                if self.log {
+                    println!("Playing a sub-game to determine the winner...\n");
+                }
+                let sub_decks = decks
+                    .iter()
+                    .zip(sizes)
+                    .map(|(deck, size)| deck.iter().take(size).copied().collect())
+                    .collect();
+                let (sub_game_winner, _) = self.play(sub_decks);
+                if self.log {
+                    println!("...anyway, back to game {}.", game_id);
+                }
+                sub_game_winner
             } else {
-                return (Player::Player1, self.score(Player::Player1));
+                active_players
+                    .iter()
+                    .copied()
+                    .max_by_key(|player| cards[*player])
+                    .unwrap_or(0)
+            };
+
+            if self.log {
+                if recursive {
+                    println!(
+                        "Player {} wins round {} of game {}!",
+                        round_winner + 1,
+                        round,
+                        game_id
+                    );
+                } else {
+                    println!("Player {} wins the round!", round_winner + 1);
+                }
             }
-        }
 
-        let winner = self.winner().unwrap();
-        (winner.clone(), self.score(winner))
+            let mut other_cards: Vec<usize> = cards
+                .iter()
+                .enumerate()
+                .filter(|(player, _)| *player != round_winner)
+                .filter_map(|(_, card)| *card)
+                .collect();
+            other_cards.sort_unstable_by(|a, b| b.cmp(a));
+            let winner_deck = &mut decks[round_winner];
+            winner_deck.extend(cards[round_winner]);
+            winner_deck.extend(other_cards);
+        };
+
+        if self.log {
+            if recursive {
+                println!("The winner of game {} is player {}!", game_id, winner + 1);
+            }
+            println!();
+        }
+        (winner, decks)
     }
 }
 
-impl RecursiveCombat {
-    fn new<I1: Iterator<Item = usize>, I2: Iterator<Item = usize>>(deck1: I1, deck2: I2) -> Self {
-        Self::new_from_deque(deck1.collect(), deck2.collect())
-    }
-    fn new_from_deque(deck1: VecDeque<usize>, deck2: VecDeque<usize>) -> Self {
-        Self {
-            player1: deck1,
-            player2: deck2,
-            rounds_decks: HashSet::new(),
+fn score(deck: &Deck) -> usize {
+    deck.iter()
+        .rev()
+        .enumerate()
+        .map(|(index, value)| value * (index + 1))
+        .sum()
+}
+
+fn parse_decks(lines: &[String]) -> Vec<Deck> {
+    lines
+        .split(String::is_empty)
+        .filter(|block| !block.is_empty())
+        .map(|block| {
+            block
+                .iter()
+                .skip(1)
+                .filter_map(|line| line.parse::<usize>().ok())
+                .collect()
+        })
+        .collect()
+}
+
+fn play_and_score(rules: impl RecursionRule, decks: Vec<Deck>, log: bool) -> usize {
+    let (winner, decks) = Game::new(rules, log).play(decks);
+    if log {
+        println!("\n== Post-game results ==");
+        for (player, deck) in decks.iter().enumerate() {
+            println!("{}", format_player_deck(player, deck));
         }
+        println!();
     }
+    score(&decks[winner])
 }
 
-fn part01(lines: &[String]) -> usize {
-    let (_, score) = CrabCombat::from(lines).play();
-    score
+fn part01(lines: &[String], log: bool) -> usize {
+    play_and_score(CrabCombat, parse_decks(lines), log)
 }
 
-fn part02(lines: &[String]) -> usize {
-    let (_, score) = RecursiveCombat::from(lines).play();
-    score
+fn part02(lines: &[String], log: bool) -> usize {
+    play_and_score(RecursiveCombat, parse_decks(lines), log)
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    println!("Part 1: {}", part01(&lines));
-    println!("Part 2: {}", part02(&lines));
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    println!("Part 1: {}", part01(&lines, opts.log));
+    println!("Part 2: {}", part02(&lines, opts.log));
 
     Ok(())
 }
@@ -1,131 +1,65 @@
+use clap::Args;
+use helpers::hex::{render, Axial, Orientation};
+use helpers::input_lines_with_opts;
 use std::collections::HashSet;
-use strum::{EnumIter, IntoEnumIterator};
-
-use std::str::FromStr;
-
-use helpers::input_lines;
 
 const INPUT: &str = include_str!("../input.txt");
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Point {
-    x: i64,
-    y: i64,
-}
-
-impl std::ops::Add for Point {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
-    }
-}
-
-impl std::ops::Sub for Point {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self {
-        Self {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
-    }
-}
-
-impl std::ops::AddAssign for Point {
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-    }
-}
-
-impl Point {
-    fn neighbours(&self) -> impl Iterator<Item = Point> + '_ {
-        Direction::iter().map(move |direction| *self + direction.offset())
-    }
-}
-
-#[derive(Debug, EnumIter)]
-enum Direction {
-    E,
-    NE,
-    NW,
-    SE,
-    SW,
-    W,
-}
-
-impl Direction {
-    fn offset(&self) -> Point {
-        match self {
-            Direction::E => Point { x: 2, y: 0 },
-            Direction::NE => Point { x: 1, y: 1 },
-            Direction::NW => Point { x: -1, y: 1 },
-            Direction::SE => Point { x: 1, y: -1 },
-            Direction::SW => Point { x: -1, y: -1 },
-            Direction::W => Point { x: -2, y: 0 },
-        }
-    }
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the floor (`#` for black tiles, `.` for white tiles) after the initial flips
+    /// and after the daily flips
+    #[arg(long)]
+    render: bool,
 }
 
 #[derive(Debug)]
-struct Path(Vec<Direction>);
+struct Path(Vec<Axial>);
 
-impl FromStr for Path {
-    type Err = ();
+impl std::str::FromStr for Path {
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut directions_str = s;
-        let mut directions = Vec::new();
-        while !directions_str.is_empty() {
-            if let Some(residual_string) = directions_str.strip_prefix('e') {
-                directions.push(Direction::E);
-                directions_str = residual_string;
-            } else if let Some(residual_string) = directions_str.strip_prefix("ne") {
-                directions.push(Direction::NE);
-                directions_str = residual_string;
-            } else if let Some(residual_string) = directions_str.strip_prefix("nw") {
-                directions.push(Direction::NW);
-                directions_str = residual_string;
-            } else if let Some(residual_string) = directions_str.strip_prefix("se") {
-                directions.push(Direction::SE);
-                directions_str = residual_string;
-            } else if let Some(residual_string) = directions_str.strip_prefix("sw") {
-                directions.push(Direction::SW);
-                directions_str = residual_string;
-            } else if let Some(residual_string) = directions_str.strip_prefix('w') {
-                directions.push(Direction::W);
-                directions_str = residual_string;
-            } else {
-                unreachable!()
-            }
-        }
-
-        Ok(Self(directions))
+        Ok(Self(Orientation::Pointy.parse_path(s)?))
     }
 }
 
 impl Path {
-    fn evaluate_point(&self) -> Point {
-        let mut point = Point { x: 0, y: 0 };
-        for direction in &self.0 {
-            point += direction.offset();
+    fn evaluate_point(&self) -> Axial {
+        let mut point = Axial::default();
+        for step in &self.0 {
+            point += *step;
         }
         point
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Floor {
-    black_tiles: HashSet<Point>,
+    black_tiles: HashSet<Axial>,
 }
 
 impl Floor {
-    fn points_with_cnt_black_neighbours<I: Iterator<Item = Point>>(
+    fn new(paths: &[Path]) -> Self {
+        let mut floor = Self {
+            black_tiles: HashSet::new(),
+        };
+
+        for path in paths {
+            let point = path.evaluate_point();
+            if !floor.black_tiles.insert(point) {
+                // Not inserting means that was already there
+                // So the tile was black and now we're flipping it again
+                floor.black_tiles.take(&point);
+            }
+        }
+        floor
+    }
+
+    fn points_with_cnt_black_neighbours<I: Iterator<Item = Axial>>(
         &self,
         points: I,
         valid_black_neighbours_count: &[usize],
-    ) -> HashSet<Point> {
+    ) -> HashSet<Axial> {
         points
             .filter(|black_tile| {
                 valid_black_neighbours_count.contains(
@@ -159,60 +93,50 @@ impl Floor {
     }
 }
 
-fn part01(directions: &[Path]) -> usize {
-    let mut floor = Floor {
-        black_tiles: HashSet::new(),
-    };
-
-    for direction in directions {
-        let point = direction.evaluate_point();
-        if !floor.black_tiles.insert(point) {
-            // Not inserting means that was already there
-            // So the tile was black and now we're flipping it again
-            floor.black_tiles.take(&point);
-        }
+impl std::fmt::Display for Floor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            render(&self.black_tiles, Orientation::Pointy, |tile| {
+                if self.black_tiles.contains(&tile) {
+                    '#'
+                } else {
+                    '.'
+                }
+            })
+        )
     }
+}
 
+fn part01(floor: &Floor) -> usize {
     floor.black_tiles.len()
 }
 
-fn part02(directions: &[Path]) -> usize {
-    let mut floor = Floor {
-        black_tiles: HashSet::new(),
-    };
-
-    for direction in directions {
-        let point = direction.evaluate_point();
-        if !floor.black_tiles.insert(point) {
-            // Not inserting means that was already there
-            // So the tile was black and now we're flipping it again
-            floor.black_tiles.take(&point);
-        }
-    }
+fn part02(floor: &Floor) -> usize {
+    floor.black_tiles.len()
+}
+
+fn main() -> anyhow::Result<()> {
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let paths: Vec<Path> = lines
+        .iter()
+        .map(|line| line.parse())
+        .collect::<Result<_, _>>()?;
 
+    let initial_floor = Floor::new(&paths);
+    let mut floor = initial_floor.clone();
     for _ in 0..100 {
         floor.round();
     }
 
-    floor.black_tiles.len()
-}
+    if opts.render {
+        println!("Initial floor:\n{}", initial_floor);
+        println!("After 100 days:\n{}", floor);
+    }
 
-fn main() -> anyhow::Result<()> {
-    let directions: Vec<Path> = input_lines(INPUT)?
-        .iter()
-        .filter_map(|line| line.parse().ok())
-        .collect();
-
-    println!("Part 1: {}", part01(&directions));
-    println!("Part 2: {}", part02(&directions));
-
-    let mut floor = Floor {
-        black_tiles: HashSet::new(),
-    };
-    floor.black_tiles.insert(Point { x: 0, y: 0 });
-    floor.black_tiles.insert(Point { x: 2, y: 0 });
-    floor.black_tiles.insert(Point { x: -2, y: 0 });
-    floor.black_tiles.insert(Point { x: 1, y: 1 });
+    println!("Part 1: {}", part01(&initial_floor));
+    println!("Part 2: {}", part02(&floor));
 
     Ok(())
 }
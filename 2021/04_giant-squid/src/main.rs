@@ -1,35 +1,95 @@
-use helpers::input_lines;
+use clap::{Args, ValueEnum};
+use helpers::input_lines_with_opts;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 const INPUT: &str = include_str!("../input.txt");
 
-type BingoNumber = u8; // Using u8 as numbers in the input are in the range 0-99
+type BingoNumber = usize;
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Patterns that make a board win (comma separated)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [WinningPattern::Rows, WinningPattern::Columns])]
+    patterns: Vec<WinningPattern>,
+
+    /// Prints every win of the game, in order
+    #[arg(long)]
+    timeline: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum WinningPattern {
+    /// Any complete row
+    Rows,
+    /// Any complete column
+    Columns,
+    /// Any of the two complete diagonals
+    Diagonals,
+    /// The four corners of the board
+    FourCorners,
+    /// All the cells of the board
+    FullCard,
+}
+
+impl WinningPattern {
+    /// Sets of cells (as row-major indexes) that make a board of the given size win, if all marked
+    fn cell_sets(self, size: usize) -> Vec<Vec<usize>> {
+        match self {
+            Self::Rows => (0..size)
+                .map(|row| (0..size).map(|column| row * size + column).collect())
+                .collect(),
+            Self::Columns => (0..size)
+                .map(|column| (0..size).map(|row| row * size + column).collect())
+                .collect(),
+            Self::Diagonals => vec![
+                (0..size).map(|index| index * size + index).collect(),
+                (0..size)
+                    .map(|index| index * size + (size - 1 - index))
+                    .collect(),
+            ],
+            Self::FourCorners => {
+                let mut corners = vec![0, size - 1, (size - 1) * size, size * size - 1];
+                corners.dedup();
+                vec![corners]
+            }
+            Self::FullCard => vec![(0..size * size).collect()],
+        }
+    }
+}
+
+/// Square board of any size
 #[derive(Debug)]
 struct Grid {
-    values: [[BingoNumber; 5]; 5],
+    size: usize,
+    /// Row-major values
+    values: Vec<BingoNumber>,
 }
 
 impl TryFrom<&[String]> for Grid {
     type Error = anyhow::Error;
 
     fn try_from(lines: &[String]) -> Result<Self, Self::Error> {
-        anyhow::ensure!(lines.len() == 5);
-
-        let mut values = [[0; 5]; 5];
+        let size = lines.len();
+        anyhow::ensure!(size > 0, "Empty board");
 
-        for (line_no, line) in lines.iter().enumerate() {
+        let mut values = Vec::with_capacity(size * size);
+        for line in lines {
             let parsed_values: Vec<BingoNumber> = line
                 .split(' ')
                 .filter(|part| !part.is_empty())
                 .map(str::parse)
                 .collect::<Result<_, _>>()?;
-            anyhow::ensure!(parsed_values.len() == 5);
-
-            values[line_no][..5].copy_from_slice(&parsed_values[..5]);
+            anyhow::ensure!(
+                parsed_values.len() == size,
+                "Boards must be square: found a row of {} values in a board with {} rows",
+                parsed_values.len(),
+                size
+            );
+            values.extend(parsed_values);
         }
 
-        Ok(Self { values })
+        Ok(Self { size, values })
     }
 }
 
@@ -43,10 +103,7 @@ impl TryFrom<Vec<String>> for Game {
     type Error = anyhow::Error;
 
     fn try_from(lines: Vec<String>) -> Result<Self, Self::Error> {
-        anyhow::ensure!(
-            (lines.len() - 1) % 6 == 0,
-            "Invalid input: number of lines do not match the expectations"
-        );
+        anyhow::ensure!(!lines.is_empty(), "Invalid input: no extractions");
 
         let extractions = lines[0]
             .split(',')
@@ -54,8 +111,9 @@ impl TryFrom<Vec<String>> for Game {
             .collect::<Result<_, _>>()?;
 
         let grids = lines[1..]
-            .chunks(6)
-            .map(|lines_chunk| Grid::try_from(&lines_chunk[1..]))
+            .split(String::is_empty)
+            .filter(|block| !block.is_empty())
+            .map(Grid::try_from)
             .collect::<Result<_, _>>()?;
 
         Ok(Self { extractions, grids })
@@ -65,63 +123,73 @@ impl TryFrom<Vec<String>> for Game {
 #[derive(Debug)]
 struct GridMetadata<'g> {
     grid: &'g Grid,
-    matched_cells: [[bool; 5]; 5],
-    matched_in_rows: [u8; 5],
-    matched_in_columns: [u8; 5],
+    cells_by_value: HashMap<BingoNumber, Vec<usize>>,
+    matched_cells: Vec<bool>,
+    /// Cell sets that make the grid win, with the number of matched cells in each of them
+    winning_sets: Vec<(Vec<usize>, usize)>,
+    /// Indexes of `winning_sets` containing each cell
+    sets_by_cell: Vec<Vec<usize>>,
     last_extracted: Option<BingoNumber>,
 }
 
-impl<'g> From<&'g Grid> for GridMetadata<'g> {
-    fn from(grid: &'g Grid) -> Self {
+impl<'g> GridMetadata<'g> {
+    fn new(grid: &'g Grid, patterns: &[WinningPattern]) -> Self {
+        let mut cells_by_value: HashMap<BingoNumber, Vec<usize>> = HashMap::new();
+        for (cell, value) in grid.values.iter().enumerate() {
+            cells_by_value.entry(*value).or_default().push(cell);
+        }
+
+        let winning_sets: Vec<(Vec<usize>, usize)> = patterns
+            .iter()
+            .flat_map(|pattern| pattern.cell_sets(grid.size))
+            .map(|cells| (cells, 0))
+            .collect();
+        let mut sets_by_cell = vec![vec![]; grid.values.len()];
+        for (set_index, (cells, _)) in winning_sets.iter().enumerate() {
+            for cell in cells {
+                sets_by_cell[*cell].push(set_index);
+            }
+        }
+
         Self {
             grid,
-            matched_cells: [[false; 5]; 5],
-            matched_in_rows: [0; 5],
-            matched_in_columns: [0; 5],
+            cells_by_value,
+            matched_cells: vec![false; grid.values.len()],
+            winning_sets,
+            sets_by_cell,
             last_extracted: None,
         }
     }
-}
 
-impl GridMetadata<'_> {
     fn register_extraction(&mut self, value: BingoNumber) {
         self.last_extracted = Some(value);
-        for (row_index, row) in self.grid.values.iter().enumerate() {
-            for (column_index, cell_value) in row.iter().enumerate() {
-                if *cell_value == value {
-                    self.matched_in_rows[row_index] += 1;
-                    self.matched_in_columns[column_index] += 1;
-                    self.matched_cells[row_index][column_index] = true;
-                    return;
+        for cell in self.cells_by_value.get(&value).into_iter().flatten() {
+            if !self.matched_cells[*cell] {
+                self.matched_cells[*cell] = true;
+                for set_index in &self.sets_by_cell[*cell] {
+                    self.winning_sets[*set_index].1 += 1;
                 }
             }
         }
     }
 
     fn winning_score(&self) -> Option<usize> {
-        if self.matched_in_rows.iter().any(|v| *v == 5)
-            || self.matched_in_columns.iter().any(|v| *v == 5)
+        if self
+            .winning_sets
+            .iter()
+            .any(|(cells, matched)| *matched == cells.len())
         {
             let sum_not_marked: usize = self
-                .matched_cells
+                .grid
+                .values
                 .iter()
-                .enumerate()
-                .flat_map(|(row_index, row)| {
-                    row.iter()
-                        .enumerate()
-                        .map(move |(column_index, cell_value)| {
-                            if *cell_value {
-                                0
-                            } else {
-                                self.grid.values[row_index][column_index] as usize
-                            }
-                        })
-                })
+                .zip(&self.matched_cells)
+                .filter(|(_, matched)| !**matched)
+                .map(|(value, _)| value)
                 .sum();
             let last_extracted = self
                 .last_extracted
-                .expect("An extracted value should be present to have a winning board")
-                as usize;
+                .expect("An extracted value should be present to have a winning board");
 
             Some(sum_not_marked * last_extracted)
         } else {
@@ -130,50 +198,86 @@ impl GridMetadata<'_> {
     }
 }
 
-fn part01(game: &Game) -> usize {
-    let mut grids_metadata: Vec<_> = game.grids.iter().map(GridMetadata::from).collect();
-    for extracted_value in &game.extractions {
-        for grid_metadata in &mut grids_metadata {
-            grid_metadata.register_extraction(*extracted_value);
-            if let Some(winning_score) = grid_metadata.winning_score() {
-                return winning_score;
-            }
-        }
-    }
-    0
+/// A board completing one of the winning patterns
+#[derive(Debug)]
+struct Win {
+    /// Index of the extraction (starting from 0) that made the board win
+    extraction_index: usize,
+    extracted_value: BingoNumber,
+    grid_index: usize,
+    score: usize,
 }
 
-fn part02(game: &Game) -> usize {
-    let mut grids_metadata: Vec<_> = game.grids.iter().map(GridMetadata::from).collect();
+impl std::fmt::Display for Win {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Extraction #{} ({}): board {} wins with score {}",
+            self.extraction_index + 1,
+            self.extracted_value,
+            self.grid_index + 1,
+            self.score
+        )
+    }
+}
 
-    let mut extractions = game.extractions.iter();
+impl Game {
+    /// Plays all the extractions, returning the wins in order. Each board wins at most once.
+    fn timeline(&self, patterns: &[WinningPattern]) -> Vec<Win> {
+        let mut grids_metadata: Vec<(usize, GridMetadata)> = self
+            .grids
+            .iter()
+            .map(|grid| GridMetadata::new(grid, patterns))
+            .enumerate()
+            .collect();
 
-    let mut last_won_score = None;
-    loop {
-        let extracted_value = extractions.next();
-        if extracted_value.is_none() || grids_metadata.is_empty() {
-            return last_won_score.expect("Expected the definition of a winning score");
-        }
-        let mut indexes_to_remove = vec![];
-        for (index, grid_metadata) in grids_metadata.iter_mut().enumerate() {
-            grid_metadata.register_extraction(
-                *extracted_value.expect("Expected to have an extracted value"),
-            );
-            if let Some(winning_score) = grid_metadata.winning_score() {
-                last_won_score = Some(winning_score);
-                indexes_to_remove.push(index);
+        let mut wins = vec![];
+        for (extraction_index, extracted_value) in self.extractions.iter().enumerate() {
+            if grids_metadata.is_empty() {
+                break;
             }
+            grids_metadata.retain_mut(|(grid_index, grid_metadata)| {
+                grid_metadata.register_extraction(*extracted_value);
+                match grid_metadata.winning_score() {
+                    Some(score) => {
+                        wins.push(Win {
+                            extraction_index,
+                            extracted_value: *extracted_value,
+                            grid_index: *grid_index,
+                            score,
+                        });
+                        false
+                    }
+                    None => true,
+                }
+            });
         }
-        for index_to_remove in indexes_to_remove.iter().rev() {
-            grids_metadata.remove(*index_to_remove);
-        }
+        wins
     }
 }
 
+fn part01(wins: &[Win]) -> anyhow::Result<usize> {
+    wins.first()
+        .map(|win| win.score)
+        .ok_or_else(|| anyhow::anyhow!("No board wins"))
+}
+
+fn part02(wins: &[Win]) -> anyhow::Result<usize> {
+    wins.last()
+        .map(|win| win.score)
+        .ok_or_else(|| anyhow::anyhow!("No board wins"))
+}
+
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
     let game: Game = Game::try_from(lines)?;
-    println!("Part 1: {}", part01(&game));
-    println!("Part 2: {}", part02(&game));
+    let wins = game.timeline(&opts.patterns);
+    if opts.timeline {
+        for win in &wins {
+            println!("{}", win);
+        }
+    }
+    println!("Part 1: {}", part01(&wins)?);
+    println!("Part 2: {}", part02(&wins)?);
     Ok(())
 }
@@ -1,66 +1,104 @@
-use helpers::input_lines;
-use std::str::FromStr;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::transition::TransitionMatrix;
+use num_bigint::BigUint;
 const INPUT: &str = include_str!("../input.txt");
 
-fn part01(mut fishes: LanternFishes) -> usize {
-    for _ in 0..80 {
-        fishes.next_generation();
-    }
-    fishes.fish_count()
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Timer of a lanternfish after it creates a new one
+    #[arg(long, default_value_t = 6)]
+    reset_timer: usize,
+
+    /// Timer of a newly created lanternfish
+    #[arg(long, default_value_t = 8)]
+    spawn_timer: usize,
+
+    /// Additionally reports the number of lanternfishes after the given number of days
+    #[arg(long)]
+    days: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Timers {
+    reset: usize,
+    spawn: usize,
 }
 
-fn part02(mut fishes: LanternFishes) -> usize {
-    for _ in 0..256 {
-        fishes.next_generation();
+impl Timers {
+    fn max(self) -> usize {
+        self.reset.max(self.spawn)
+    }
+
+    /// Transition between the number of lanternfishes with each timer value in one day
+    fn transition_matrix(self) -> TransitionMatrix {
+        let mut matrix = TransitionMatrix::zeros(self.max() + 1);
+        matrix.add(0, self.reset, 1);
+        matrix.add(0, self.spawn, 1);
+        for timer in 1..=self.max() {
+            matrix.add(timer, timer - 1, 1);
+        }
+        matrix
     }
-    fishes.fish_count()
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug)]
 struct LanternFishes {
-    time_before_reproduction: [usize; 9],
+    timers: Timers,
+    /// Number of lanternfishes for each timer value
+    time_before_reproduction: Vec<BigUint>,
 }
 
-impl FromStr for LanternFishes {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<usize> = s.split(',').map(str::parse).collect::<Result<_, _>>()?;
-        let time_before_reproduction =
-            parts
-                .iter()
-                .fold([0; 9], |mut time_before_reproduction, value| {
-                    time_before_reproduction[*value] += 1;
-                    time_before_reproduction
-                });
+impl LanternFishes {
+    fn new(line: &str, timers: Timers) -> Result<Self, anyhow::Error> {
+        let mut time_before_reproduction = vec![BigUint::default(); timers.max() + 1];
+        for part in line.split(',') {
+            let value: usize = part.parse()?;
+            anyhow::ensure!(
+                value <= timers.max(),
+                "Timer {value} is bigger than the maximum timer ({max})",
+                value = value,
+                max = timers.max()
+            );
+            time_before_reproduction[value] += 1_u8;
+        }
         Ok(Self {
+            timers,
             time_before_reproduction,
         })
     }
-}
 
-impl LanternFishes {
-    fn next_generation(&mut self) {
-        let lantern_fishes_in_reproduction = self.time_before_reproduction[0];
+    fn fish_count_after(&self, days: u64) -> BigUint {
+        self.timers
+            .transition_matrix()
+            .state_after(&self.time_before_reproduction, days)
+            .iter()
+            .sum()
+    }
+}
 
-        for index in 0..8 {
-            self.time_before_reproduction[index] = self.time_before_reproduction[index + 1];
-        }
+fn part01(fishes: &LanternFishes) -> BigUint {
+    fishes.fish_count_after(80)
+}
 
-        self.time_before_reproduction[6] += lantern_fishes_in_reproduction;
-        self.time_before_reproduction[8] = lantern_fishes_in_reproduction;
-    }
-    fn fish_count(&self) -> usize {
-        self.time_before_reproduction.iter().sum()
-    }
+fn part02(fishes: &LanternFishes) -> BigUint {
+    fishes.fish_count_after(256)
 }
+
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
     anyhow::ensure!(lines.len() == 1);
 
-    let fishes: LanternFishes = lines[0].parse()?;
+    let timers = Timers {
+        reset: opts.reset_timer,
+        spawn: opts.spawn_timer,
+    };
+    let fishes = LanternFishes::new(&lines[0], timers)?;
 
-    println!("Part 1: {}", part01(fishes));
-    println!("Part 2: {}", part02(fishes));
+    println!("Part 1: {}", part01(&fishes));
+    println!("Part 2: {}", part02(&fishes));
+    if let Some(days) = opts.days {
+        println!("After {} days: {}", days, fishes.fish_count_after(days));
+    }
     Ok(())
 }
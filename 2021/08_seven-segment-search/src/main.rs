@@ -1,13 +1,194 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::collections::BTreeSet;
 use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the wire mapping and the decoded value of each entry
+    #[arg(long)]
+    mappings: bool,
+}
+
+/// Set of segments (or wires), `a` is the least significant bit
+type Segments = u8;
+
+const SEGMENTS_COUNT: usize = 7;
+const ALL_SEGMENTS: Segments = (1 << SEGMENTS_COUNT) - 1;
+
+/// Segments lit to display each digit
+const DIGITS: [Segments; 10] = [
+    0b111_0111, // abcefg
+    0b010_0100, // cf
+    0b101_1101, // acdeg
+    0b110_1101, // acdfg
+    0b010_1110, // bcdf
+    0b110_1011, // abdfg
+    0b111_1011, // abdefg
+    0b010_0101, // acf
+    0b111_1111, // abcdefg
+    0b110_1111, // abcdfg
+];
+
+fn parse_segments(value: &str) -> Result<Segments, anyhow::Error> {
+    value.bytes().try_fold(0, |segments, letter| match letter {
+        b'a'..=b'g' => Ok(segments | (1 << (letter - b'a'))),
+        _ => Err(anyhow::anyhow!(
+            "Unrecognized segment `{letter}` in `{value}`",
+            letter = letter as char,
+            value = value,
+        )),
+    })
+}
+
+#[inline]
+fn segment_name(segment: usize) -> char {
+    (b'a' + segment as u8) as char
+}
+
+/// Permutation connecting each wire to the segment it lights
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct WireMapping {
+    segment_of_wire: [usize; SEGMENTS_COUNT],
+}
+
+impl WireMapping {
+    fn translate(&self, wires: Segments) -> Segments {
+        (0..SEGMENTS_COUNT)
+            .filter(|wire| wires & (1 << wire) != 0)
+            .fold(0, |segments, wire| {
+                segments | (1 << self.segment_of_wire[wire])
+            })
+    }
+
+    fn digit(&self, wires: Segments) -> Option<u8> {
+        let segments = self.translate(wires);
+        DIGITS
+            .iter()
+            .position(|digit| *digit == segments)
+            .map(|digit| digit as u8)
+    }
+}
+
+impl std::fmt::Display for WireMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (wire, segment) in self.segment_of_wire.iter().enumerate() {
+            if wire > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}->{}", segment_name(wire), segment_name(*segment))?;
+        }
+        Ok(())
+    }
+}
+
+/// Restricts the segments each wire can be connected to, so that every observed pattern
+/// can still represent a digit and the wires are connected to distinct segments.
+///
+/// Returns `false` if the observations are contradictory.
+fn propagate(domains: &mut [Segments; SEGMENTS_COUNT], patterns: &[Segments]) -> bool {
+    loop {
+        let before = *domains;
+
+        for pattern in patterns {
+            let is_wire_lit = |wire: usize| pattern & (1 << wire) != 0;
+            let (lit_union, unlit_union) = DIGITS
+                .iter()
+                .filter(|digit| digit.count_ones() == pattern.count_ones())
+                .filter(|digit| {
+                    (0..SEGMENTS_COUNT).all(|wire| {
+                        let allowed = if is_wire_lit(wire) {
+                            **digit
+                        } else {
+                            !**digit & ALL_SEGMENTS
+                        };
+                        domains[wire] & allowed != 0
+                    })
+                })
+                .fold((0, 0), |(lit_union, unlit_union), digit| {
+                    (lit_union | digit, unlit_union | (!digit & ALL_SEGMENTS))
+                });
+            for (wire, domain) in domains.iter_mut().enumerate() {
+                *domain &= if is_wire_lit(wire) {
+                    lit_union
+                } else {
+                    unlit_union
+                };
+            }
+        }
+
+        // A wire with a known segment excludes that segment from the other wires
+        for wire in 0..SEGMENTS_COUNT {
+            if domains[wire].count_ones() == 1 {
+                for other_wire in (0..SEGMENTS_COUNT).filter(|other_wire| *other_wire != wire) {
+                    domains[other_wire] &= !domains[wire];
+                }
+            }
+        }
+
+        // A segment that can be connected to a single wire has to be connected to it
+        for segment in 0..SEGMENTS_COUNT {
+            let mut wires = (0..SEGMENTS_COUNT).filter(|wire| domains[*wire] & (1 << segment) != 0);
+            match (wires.next(), wires.next()) {
+                (None, _) => return false,
+                (Some(wire), None) => domains[wire] = 1 << segment,
+                _ => {}
+            }
+        }
+
+        if domains.contains(&0) {
+            return false;
+        }
+        if *domains == before {
+            return true;
+        }
+    }
+}
+
+fn search(
+    mut domains: [Segments; SEGMENTS_COUNT],
+    patterns: &[Segments],
+    solutions: &mut Vec<WireMapping>,
+) {
+    if !propagate(&mut domains, patterns) {
+        return;
+    }
+
+    let undecided_wire = (0..SEGMENTS_COUNT)
+        .filter(|wire| domains[*wire].count_ones() > 1)
+        .min_by_key(|wire| domains[*wire].count_ones());
+    match undecided_wire {
+        None => {
+            let mut segment_of_wire = [0; SEGMENTS_COUNT];
+            for (wire, domain) in domains.iter().enumerate() {
+                segment_of_wire[wire] = domain.trailing_zeros() as usize;
+            }
+            let mapping = WireMapping { segment_of_wire };
+            if patterns
+                .iter()
+                .all(|pattern| mapping.digit(*pattern).is_some())
+            {
+                solutions.push(mapping);
+            }
+        }
+        Some(wire) => {
+            for segment in (0..SEGMENTS_COUNT).filter(|segment| domains[wire] & (1 << segment) != 0)
+            {
+                let mut next_domains = domains;
+                next_domains[wire] = 1 << segment;
+                search(next_domains, patterns, solutions);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 struct InputEntry {
-    one_representation: Vec<char>,
-    four_representation: Vec<char>,
-    four_displays: [Vec<char>; 4],
+    /// Observed signal patterns, some of them might be missing
+    signal_patterns: Vec<Segments>,
+    displays: Vec<Segments>,
 }
 
 impl FromStr for InputEntry {
@@ -15,54 +196,19 @@ impl FromStr for InputEntry {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((combinations, displays)) = s.split_once('|') {
-            const EMPTY: Vec<char> = vec![];
-            let (one_representation, four_representation) = {
-                let (maybe_one_representation, maybe_four_representation) =
-                    combinations.split_whitespace().fold(
-                        (None, None),
-                        move |(maybe_one_representation, maybe_four_representation), segments| {
-                            let values: Vec<char> = segments.chars().collect();
-
-                            match values.len() {
-                                2 => (Some(values), maybe_four_representation),
-                                4 => (maybe_one_representation, Some(values)),
-                                _ => (maybe_one_representation, maybe_four_representation),
-                            }
-                        },
-                    );
-
-                if let Some(one_representation) = maybe_one_representation {
-                    if let Some(four_representation) = maybe_four_representation {
-                        Ok((one_representation, four_representation))
-                    } else {
-                        Err(anyhow::anyhow!(
-                            "Representation of `4` not found in the input"
-                        ))
-                    }
-                } else {
-                    Err(anyhow::anyhow!(
-                        "Representation of `1` not found in the input"
-                    ))
-                }
-            }?;
-
-            let four_displays: [Vec<char>; 4] = {
-                let segments_vec: Vec<Vec<char>> = displays
-                    .split_whitespace()
-                    .map(|segments| segments.chars().collect())
-                    .collect();
-                anyhow::ensure!(segments_vec.len() == 4);
-
-                let mut segments: [Vec<char>; 4] = [EMPTY; 4];
-                segments.clone_from_slice(&segments_vec);
-
-                segments
-            };
+            let signal_patterns = combinations
+                .split_whitespace()
+                .map(parse_segments)
+                .collect::<Result<_, _>>()?;
+            let displays: Vec<Segments> = displays
+                .split_whitespace()
+                .map(parse_segments)
+                .collect::<Result<_, _>>()?;
+            anyhow::ensure!(!displays.is_empty(), "Invalid input: no displays");
 
             Ok(Self {
-                one_representation,
-                four_representation,
-                four_displays,
+                signal_patterns,
+                displays,
             })
         } else {
             Err(anyhow::anyhow!("Invalid input: expected `|` to be present"))
@@ -71,6 +217,33 @@ impl FromStr for InputEntry {
 }
 
 impl InputEntry {
+    /// All the wire mappings consistent with the signal patterns and the displays
+    fn mappings(&self) -> Vec<WireMapping> {
+        let patterns: Vec<Segments> = self
+            .signal_patterns
+            .iter()
+            .chain(&self.displays)
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut solutions = vec![];
+        search([ALL_SEGMENTS; SEGMENTS_COUNT], &patterns, &mut solutions);
+        solutions
+    }
+
+    /// The only wire mapping consistent with the observations
+    fn mapping(&self) -> Result<WireMapping, anyhow::Error> {
+        match self.mappings().as_slice() {
+            [] => Err(anyhow::anyhow!("No wire mapping explains the observations")),
+            [mapping] => Ok(*mapping),
+            mappings => Err(anyhow::anyhow!(
+                "{count} wire mappings explain the observations",
+                count = mappings.len()
+            )),
+        }
+    }
+
     fn to_number(&self) -> Result<usize, anyhow::Error> {
         Ok(self
             .digits()?
@@ -78,61 +251,25 @@ impl InputEntry {
             .fold(0, |result, value| result * 10 + *value as usize))
     }
 
-    fn digits(&self) -> Result<[u8; 4], anyhow::Error> {
-        let mut result = [0; 4];
-        for (index, display_segments) in self.four_displays.iter().enumerate() {
-            let number = match display_segments.len() {
-                2 => 1,
-                4 => 4,
-                3 => 7,
-                7 => 8,
-                5 => {
-                    match display_segments.iter().filter(|segment| self.four_representation.contains(segment)).count() {
-                        2 => 2,
-                        3 => {
-                            match display_segments.iter().filter(|segment| self.one_representation.contains(segment)).count() {
-                                1 => 5,
-                                2 => 3,
-                                val => anyhow::bail!(
-                                    "It shuld not be possible to have a 5 segments digits matching which overlaps for {val} segments with a 1 representation",
-                                    val = val,
-                                ),
-                            }
-                        },
-                        val => anyhow::bail!(
-                            "It shuld not be possible to have a 5 segments digits matching which overlaps for {val} segments with a 4 representation",
-                            val = val,
-                        )
-                    }
-                }
-                6 => {
-                    match display_segments.iter().filter(|segment| self.four_representation.contains(segment)).count() {
-                        3 => {
-                            match display_segments.iter().filter(|segment| self.one_representation.contains(segment)).count() {
-                                1 => 6,
-                                2 => 0,
-                                val => anyhow::bail!(
-                                    "It shuld not be possible to have a 6 segments digits matching which overlaps for {val} segments with a 1 representation",
-                                    val = val,
-                                ),
-                            }
-                        },
-                        4 => 9,
-                        val => anyhow::bail!(
-                            "It shuld not be possible to have a 6 segments digits matching which overlaps for {val} segments with a 4 representation",
-                            val = val,
-                        )
-                    }
-                }
-                val => anyhow::bail!(
-                    "It should not be possible to have a display with {val} segments on",
-                    val = val
-                ),
-            };
-            result[index] = number;
+    /// Decodes the displays. Multiple wire mappings are accepted as long as they agree on the displayed digits.
+    fn digits(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let mut decoded_displays = self.mappings().into_iter().map(|mapping| {
+            self.displays
+                .iter()
+                .map(|display| mapping.digit(*display).unwrap_or_default())
+                .collect::<Vec<_>>()
+        });
+        let digits = decoded_displays
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No wire mapping explains the observations"))?;
+        if let Some(other_digits) = decoded_displays.find(|other_digits| *other_digits != digits) {
+            anyhow::bail!(
+                "The displays are ambiguous: they could show {digits:?} or {other_digits:?}",
+                digits = digits,
+                other_digits = other_digits,
+            );
         }
-
-        Ok(result)
+        Ok(digits)
     }
 }
 
@@ -157,10 +294,20 @@ fn part02(input: &[InputEntry]) -> usize {
 }
 
 fn main() -> anyhow::Result<()> {
-    let input: Vec<InputEntry> = input_lines(INPUT)?
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input: Vec<InputEntry> = lines
         .iter()
         .map(|line| line.parse())
         .collect::<Result<_, _>>()?;
+    if opts.mappings {
+        for (line, entry) in lines.iter().zip(&input) {
+            match (entry.mapping(), entry.to_number()) {
+                (Ok(mapping), Ok(number)) => println!("{}: {} => {}", line, mapping, number),
+                (Err(error), Ok(number)) => println!("{}: {} => {}", line, error, number),
+                (_, Err(error)) => println!("{}: {}", line, error),
+            }
+        }
+    }
     println!("Part 1: {}", part01(&input));
     println!("Part 2: {}", part02(&input));
     Ok(())
@@ -1,166 +1,248 @@
-use helpers::input_lines;
-use std::convert::TryFrom;
-use std::ops::Deref;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
-#[derive(Clone, Copy, Debug)]
-enum Bracket {
-    OpenRound,
-    CloseRound,
-    OpenSquare,
-    CloseSquare,
-    OpenCurly,
-    CloseCurly,
-    OpenAngle,
-    CloseAngle,
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Bracket pairs, as a sequence of opening and closing characters
+    #[arg(long, default_value = "()[]{}<>")]
+    pairs: Brackets,
+
+    /// Prints the diagnostic of each line
+    #[arg(long)]
+    diagnostics: bool,
+
+    /// Prints each line after fixing the corrupted brackets and completing it
+    #[arg(long)]
+    fix: bool,
 }
 
-impl TryFrom<char> for Bracket {
-    type Error = anyhow::Error;
-
-    fn try_from(value: char) -> Result<Self, Self::Error> {
-        match value {
-            '(' => Ok(Self::OpenRound),
-            ')' => Ok(Self::CloseRound),
-            '[' => Ok(Self::OpenSquare),
-            ']' => Ok(Self::CloseSquare),
-            '{' => Ok(Self::OpenCurly),
-            '}' => Ok(Self::CloseCurly),
-            '<' => Ok(Self::OpenAngle),
-            '>' => Ok(Self::CloseAngle),
-            _ => Err(anyhow::anyhow!("Unexpected:  {value}", value = value)),
+#[derive(Clone, Debug)]
+struct Brackets {
+    /// Opening and closing character of each pair
+    pairs: Vec<(char, char)>,
+}
+
+impl FromStr for Brackets {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        anyhow::ensure!(
+            !chars.is_empty() && chars.len().is_multiple_of(2),
+            "Expected a non empty sequence of opening and closing characters, found `{s}`",
+            s = s
+        );
+        for (index, c) in chars.iter().enumerate() {
+            anyhow::ensure!(
+                !chars[index + 1..].contains(c),
+                "`{c}` is used by multiple bracket pairs",
+                c = c
+            );
         }
+        Ok(Self {
+            pairs: chars.chunks(2).map(|pair| (pair[0], pair[1])).collect(),
+        })
     }
 }
 
-impl Bracket {
-    fn is_opening(self) -> bool {
-        matches!(
-            self,
-            Self::OpenRound | Self::OpenSquare | Self::OpenCurly | Self::OpenAngle
-        )
-    }
+#[derive(Clone, Copy, Debug)]
+enum Token {
+    /// Opening bracket, with the matching closing character
+    Open(char),
+    Close(char),
+}
 
-    fn is_closing_of(self, other: Bracket) -> bool {
-        matches!(
-            (self, other),
-            (Self::CloseRound, Self::OpenRound)
-                | (Self::CloseSquare, Self::OpenSquare)
-                | (Self::CloseCurly, Self::OpenCurly)
-                | (Self::CloseAngle, Self::OpenAngle)
-        )
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum Diagnostic {
+    Valid,
+    /// A closing bracket does not match the last open one
+    Corrupted {
+        position: usize,
+        found: char,
+        /// `None` if no bracket was open
+        expected: Option<char>,
+    },
+    /// Some brackets are not closed. `completion` closes them
+    Incomplete {
+        completion: String,
+    },
+}
 
-    fn score_if_incorrect(self) -> usize {
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::CloseRound => 3,
-            Self::CloseSquare => 57,
-            Self::CloseCurly => 1197,
-            Self::CloseAngle => 25137,
-            _ => 0,
+            Self::Valid => write!(f, "Valid"),
+            Self::Corrupted {
+                position,
+                found,
+                expected: Some(expected),
+            } => write!(
+                f,
+                "Expected {}, but found {} instead (position {})",
+                expected, found, position
+            ),
+            Self::Corrupted {
+                position,
+                found,
+                expected: None,
+            } => write!(
+                f,
+                "Found {} with no open bracket (position {})",
+                found, position
+            ),
+            Self::Incomplete { completion } => write!(f, "Complete by adding {}", completion),
         }
     }
+}
 
-    fn to_closing(self) -> Self {
-        match self {
-            Self::OpenRound => Self::CloseRound,
-            Self::OpenSquare => Self::CloseSquare,
-            Self::OpenCurly => Self::CloseCurly,
-            Self::OpenAngle => Self::CloseAngle,
-            val => val,
-        }
+impl Brackets {
+    fn token(&self, c: char, position: usize) -> Result<Token, anyhow::Error> {
+        self.pairs
+            .iter()
+            .find_map(|(open, close)| {
+                if c == *open {
+                    Some(Token::Open(*close))
+                } else if c == *close {
+                    Some(Token::Close(*close))
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unexpected: {c} (position {position})",
+                    c = c,
+                    position = position
+                )
+            })
     }
 
-    fn score_if_filling(self) -> usize {
-        match self {
-            Self::CloseRound => 1,
-            Self::CloseSquare => 2,
-            Self::CloseCurly => 3,
-            Self::CloseAngle => 4,
-            _ => 0,
+    fn diagnose(&self, line: &str) -> Result<Diagnostic, anyhow::Error> {
+        // Closing characters of the open brackets
+        let mut stack: Vec<char> = vec![];
+
+        for (position, c) in line.chars().enumerate() {
+            match self.token(c, position)? {
+                Token::Open(close) => stack.push(close),
+                Token::Close(close) => {
+                    let expected = stack.pop();
+                    if expected != Some(close) {
+                        return Ok(Diagnostic::Corrupted {
+                            position,
+                            found: close,
+                            expected,
+                        });
+                    }
+                }
+            }
         }
-    }
-}
 
-#[derive(Debug)]
-struct Chunk {
-    inner: Vec<Bracket>,
-}
+        if stack.is_empty() {
+            Ok(Diagnostic::Valid)
+        } else {
+            Ok(Diagnostic::Incomplete {
+                completion: stack.iter().rev().collect(),
+            })
+        }
+    }
 
-impl Deref for Chunk {
-    type Target = Vec<Bracket>;
+    /// Replaces every mismatching closing bracket with the expected one (or drops it if no bracket
+    /// is open) and completes the line
+    fn fix(&self, line: &str) -> Result<String, anyhow::Error> {
+        let mut stack: Vec<char> = vec![];
+        let mut fixed = String::with_capacity(line.len());
+
+        for (position, c) in line.chars().enumerate() {
+            match self.token(c, position)? {
+                Token::Open(close) => {
+                    stack.push(close);
+                    fixed.push(c);
+                }
+                Token::Close(_) => {
+                    if let Some(expected) = stack.pop() {
+                        fixed.push(expected);
+                    }
+                }
+            }
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+        fixed.extend(stack.iter().rev());
+        Ok(fixed)
     }
 }
 
-impl TryFrom<&String> for Chunk {
-    type Error = anyhow::Error;
-
-    fn try_from(value: &String) -> Result<Self, Self::Error> {
-        Ok(Self {
-            inner: value
-                .chars()
-                .map(Bracket::try_from)
-                .collect::<Result<_, _>>()?,
-        })
+/// Brackets outside of the ones of the assignment do not contribute to the score
+fn syntax_error_score(close: char) -> usize {
+    match close {
+        ')' => 3,
+        ']' => 57,
+        '}' => 1197,
+        '>' => 25137,
+        _ => 0,
     }
 }
 
-impl Chunk {
-    fn residual_stack(&self) -> Result<Vec<Bracket>, Bracket> {
-        let mut stack: Vec<Bracket> = vec![];
-
-        for bracket in self.iter() {
-            if bracket.is_opening() {
-                stack.push(*bracket);
-            } else if let Some(stack_head) = stack.last() {
-                if bracket.is_closing_of(*stack_head) {
-                    stack.pop();
-                } else {
-                    return Err(*bracket);
-                }
-            } else {
-                return Err(*bracket);
+fn completion_score(completion: &str) -> usize {
+    completion.chars().fold(0, |result, close| {
+        result * 5
+            + match close {
+                ')' => 1,
+                ']' => 2,
+                '}' => 3,
+                '>' => 4,
+                _ => 0,
             }
-        }
-
-        Ok(stack)
-    }
+    })
 }
 
-fn part01(chunks: &[Chunk]) -> usize {
-    chunks
+fn part01(diagnostics: &[Diagnostic]) -> usize {
+    diagnostics
         .iter()
-        .filter_map(|chunk| chunk.residual_stack().err())
-        .map(Bracket::score_if_incorrect)
+        .map(|diagnostic| match diagnostic {
+            Diagnostic::Corrupted { found, .. } => syntax_error_score(*found),
+            _ => 0,
+        })
         .sum()
 }
 
-fn part02(chunks: &[Chunk]) -> usize {
-    let mut scores: Vec<_> = chunks
+fn part02(diagnostics: &[Diagnostic]) -> usize {
+    let mut scores: Vec<_> = diagnostics
         .iter()
-        .filter_map(|chunk| chunk.residual_stack().ok())
-        .map(|residual| {
-            residual.iter().rev().fold(0, |result, bracket| {
-                result * 5 + bracket.to_closing().score_if_filling()
-            })
+        .filter_map(|diagnostic| match diagnostic {
+            Diagnostic::Incomplete { completion } => Some(completion_score(completion)),
+            _ => None,
         })
         .collect();
 
+    if scores.is_empty() {
+        return 0;
+    }
     let central_index = scores.len() / 2;
     *scores.select_nth_unstable(central_index).1
 }
 
 fn main() -> anyhow::Result<()> {
-    let chunks = input_lines(INPUT)?
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let diagnostics = lines
         .iter()
-        .map(Chunk::try_from)
-        .collect::<Result<Vec<Chunk>, _>>()?;
+        .map(|line| opts.pairs.diagnose(line))
+        .collect::<Result<Vec<Diagnostic>, _>>()?;
+
+    if opts.diagnostics {
+        for (line, diagnostic) in lines.iter().zip(&diagnostics) {
+            println!("{} - {}", line, diagnostic);
+        }
+    }
+    if opts.fix {
+        for line in &lines {
+            println!("{}", opts.pairs.fix(line)?);
+        }
+    }
 
-    println!("Part 1: {}", part01(&chunks));
-    println!("Part 2: {}", part02(&chunks));
+    println!("Part 1: {}", part01(&diagnostics));
+    println!("Part 2: {}", part02(&diagnostics));
     Ok(())
 }
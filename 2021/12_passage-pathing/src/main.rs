@@ -1,4 +1,5 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -6,6 +7,26 @@ use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Number of times each small cave can be visited by the custom policy
+    #[arg(long, default_value_t = 1)]
+    max_visits: u8,
+
+    /// Number of times a single small cave can be visited by the custom policy
+    /// (defaults to `--max-visits`)
+    #[arg(long)]
+    max_single_visits: Option<u8>,
+
+    /// Counts the paths allowed by the custom policy
+    #[arg(long)]
+    count: bool,
+
+    /// Prints the paths allowed by the custom policy
+    #[arg(long)]
+    paths: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 enum Cell {
     Start,
@@ -30,127 +51,246 @@ impl FromStr for Cell {
     }
 }
 
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Start => write!(f, "start"),
+            Self::End => write!(f, "end"),
+            Self::BigCave(name) | Self::SmallCave(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 type CellId = usize;
 
+const START: CellId = 0;
+const END: CellId = 1;
+
+/// Limits on the visits of small caves: each small cave can be visited up to `max_visits` times,
+/// except a single one which can be visited up to `max_single_visits` times.
+#[derive(Clone, Copy, Debug)]
+struct VisitPolicy {
+    max_visits: u8,
+    max_single_visits: u8,
+}
+
+impl VisitPolicy {
+    fn new(max_visits: u8, max_single_visits: u8) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            max_single_visits >= max_visits,
+            "A single small cave cannot be visited less ({}) than any other small cave ({})",
+            max_single_visits,
+            max_visits
+        );
+        Ok(Self {
+            max_visits,
+            max_single_visits,
+        })
+    }
+
+    /// Bits needed to store the visits of a small cave
+    fn bits_per_cave(self) -> u32 {
+        u8::BITS - self.max_single_visits.leading_zeros()
+    }
+}
+
+impl std::fmt::Display for VisitPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "up to {} visits per small cave, {} for a single one",
+            self.max_visits, self.max_single_visits
+        )
+    }
+}
+
+/// Visits of the small caves, packed in a bitmask with `bits_per_cave` bits for each small cave
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+struct Visits {
+    packed: u64,
+    /// Whether a small cave has been visited more than `max_visits` times
+    single_cave_exceeded: bool,
+}
+
 #[derive(Debug)]
 struct Graph {
     cells: Vec<Cell>,
-    links: HashMap<CellId, Vec<CellId>>,
+    cell_ids: HashMap<Cell, CellId>,
+    links: Vec<Vec<CellId>>,
+    /// Index of each cell among the small caves, if it is a small cave
+    small_cave_indexes: Vec<Option<u32>>,
 }
 
 impl Graph {
     fn cell_id(&mut self, cell: Cell) -> CellId {
-        let maybe_index = self
-            .cells
-            .iter()
-            .enumerate()
-            .find_map(|(index, know_cell)| {
-                if &cell == know_cell {
-                    Some(index)
-                } else {
-                    None
-                }
-            });
-
-        if let Some(index) = maybe_index {
-            index
-        } else {
-            self.cells.push(cell);
-            self.cells.len() - 1
+        if let Some(cell_id) = self.cell_ids.get(&cell) {
+            return *cell_id;
         }
+
+        let cell_id = self.cells.len();
+        let small_cave_count = self.small_cave_indexes.iter().flatten().count() as u32;
+        self.small_cave_indexes.push(match cell {
+            Cell::SmallCave(_) => Some(small_cave_count),
+            _ => None,
+        });
+        self.cells.push(cell.clone());
+        self.cell_ids.insert(cell, cell_id);
+        self.links.push(vec![]);
+        cell_id
     }
 
-    fn register_link(&mut self, cell1: Cell, cell2: Cell) {
+    fn register_link(&mut self, cell1: Cell, cell2: Cell) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            !matches!((&cell1, &cell2), (Cell::BigCave(_), Cell::BigCave(_))),
+            "Big caves {} and {} are linked, so there are infinite paths",
+            cell1,
+            cell2
+        );
         let cell_id1 = self.cell_id(cell1);
         let cell_id2 = self.cell_id(cell2);
-        self.links.entry(cell_id1).or_default().push(cell_id2);
-        self.links.entry(cell_id2).or_default().push(cell_id1);
+        self.links[cell_id1].push(cell_id2);
+        self.links[cell_id2].push(cell_id1);
+        Ok(())
+    }
+
+    fn ensure_fits(&self, policy: VisitPolicy) -> anyhow::Result<()> {
+        let small_cave_count = self.small_cave_indexes.iter().flatten().count() as u32;
+        anyhow::ensure!(
+            small_cave_count * policy.bits_per_cave() <= u64::BITS,
+            "Too many small caves ({}) to track up to {} visits",
+            small_cave_count,
+            policy.max_single_visits
+        );
+        Ok(())
     }
 
-    fn cell(&self, cell_id: CellId) -> &Cell {
-        &self.cells[cell_id]
+    /// Visits after moving into `cell_id`, if the move is allowed by the policy
+    fn enter(&self, policy: VisitPolicy, visits: Visits, cell_id: CellId) -> Option<Visits> {
+        if cell_id == START {
+            return None;
+        }
+        let Some(small_cave_index) = self.small_cave_indexes[cell_id] else {
+            return Some(visits);
+        };
+
+        let shift = small_cave_index * policy.bits_per_cave();
+        let mask = (1_u64 << policy.bits_per_cave()) - 1;
+        let cave_visits = ((visits.packed >> shift) & mask) as u8 + 1;
+        let single_cave_exceeded = if cave_visits <= policy.max_visits {
+            visits.single_cave_exceeded
+        } else if cave_visits <= policy.max_single_visits && !visits.single_cave_exceeded {
+            true
+        } else {
+            return None;
+        };
+        Some(Visits {
+            packed: visits.packed + (1 << shift),
+            single_cave_exceeded,
+        })
     }
 
-    fn find_all_paths(&self, allow_double_small_cavern_visit: bool) -> Vec<Vec<CellId>> {
-        fn recurse(
+    /// Number of paths from `start` to `end`, without building them.
+    ///
+    /// The number of paths reaching `end` from a cell depends only on the cell and on the
+    /// visits so far, so the counts are memoized on them.
+    fn count_paths(&self, policy: VisitPolicy) -> anyhow::Result<u64> {
+        fn count(
             graph: &Graph,
-            known_paths: &mut Vec<Vec<CellId>>,
-            current_path: &mut Vec<CellId>,
-            allow_double_small_cavern_visit: bool,
-        ) {
-            let last_cell_id = current_path
-                .last()
-                .expect("Current path is expected to have at least one cell");
-
-            if last_cell_id == &1 {
-                known_paths.push(current_path.clone());
-            } else {
-                for neighbour_cell_id in &graph.links[last_cell_id] {
-                    let (is_multiple_small_cavern_visit, should_add) =
-                        if current_path.contains(neighbour_cell_id) {
-                            match graph.cell(*neighbour_cell_id) {
-                                Cell::Start => (false, false),
-                                Cell::SmallCave(_) => {
-                                    let is_small_cavern_already_visited = current_path
-                                        .iter()
-                                        .filter(|cell_id| cell_id == &neighbour_cell_id)
-                                        .count()
-                                        > 0;
-
-                                    if is_small_cavern_already_visited {
-                                        if allow_double_small_cavern_visit {
-                                            (true, true)
-                                        } else {
-                                            (true, false)
-                                        }
-                                    } else {
-                                        (false, true)
-                                    }
-                                }
-                                Cell::End | Cell::BigCave(_) => (false, true),
-                            }
-                        } else {
-                            (false, true)
-                        };
-
-                    if should_add {
-                        current_path.push(*neighbour_cell_id);
-                        recurse(
-                            graph,
-                            known_paths,
-                            current_path,
-                            allow_double_small_cavern_visit && !is_multiple_small_cavern_visit,
-                        );
-                        current_path.pop();
-                    }
-                }
+            policy: VisitPolicy,
+            cell_id: CellId,
+            visits: Visits,
+            memo: &mut HashMap<(CellId, Visits), u64>,
+        ) -> u64 {
+            if cell_id == END {
+                return 1;
             }
+            if let Some(paths) = memo.get(&(cell_id, visits)) {
+                return *paths;
+            }
+            let paths = graph.links[cell_id]
+                .iter()
+                .filter_map(|neighbour| {
+                    graph
+                        .enter(policy, visits, *neighbour)
+                        .map(|visits| count(graph, policy, *neighbour, visits, memo))
+                })
+                .sum();
+            memo.insert((cell_id, visits), paths);
+            paths
         }
 
-        let mut known_paths = vec![];
-
-        // Pre-allocate the vector containing the current path to a length
-        // which would reduce allocation along the way.
-        // Using 2 times the number of cells is just an empirical measure
-        let mut current_path = Vec::with_capacity(self.cells.len() * 2);
-        current_path.push(0);
-        recurse(
+        self.ensure_fits(policy)?;
+        Ok(count(
             self,
-            &mut known_paths,
-            &mut current_path,
-            allow_double_small_cavern_visit,
-        );
+            policy,
+            START,
+            Visits::default(),
+            &mut HashMap::new(),
+        ))
+    }
+
+    /// Lazily enumerates the paths from `start` to `end`
+    fn paths(&self, policy: VisitPolicy) -> anyhow::Result<Paths<'_>> {
+        self.ensure_fits(policy)?;
+        Ok(Paths {
+            graph: self,
+            policy,
+            stack: vec![(START, 0, Visits::default())],
+        })
+    }
+
+    fn format_path(&self, path: &[CellId]) -> String {
+        path.iter()
+            .map(|cell_id| self.cells[*cell_id].to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
 
-        known_paths
+/// Depth first exploration of the paths, yielding a path every time `end` is reached
+#[derive(Debug)]
+struct Paths<'a> {
+    graph: &'a Graph,
+    policy: VisitPolicy,
+    /// Cells of the current path, with the next neighbour to explore and the visits so far
+    stack: Vec<(CellId, usize, Visits)>,
+}
+
+impl Iterator for Paths<'_> {
+    type Item = Vec<CellId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (cell_id, next_neighbour, visits) = self.stack.last_mut()?;
+            if *cell_id == END {
+                let path = self.stack.iter().map(|(cell_id, _, _)| *cell_id).collect();
+                self.stack.pop();
+                return Some(path);
+            }
+
+            if let Some(neighbour) = self.graph.links[*cell_id].get(*next_neighbour).copied() {
+                *next_neighbour += 1;
+                if let Some(visits) = self.graph.enter(self.policy, *visits, neighbour) {
+                    self.stack.push((neighbour, 0, visits));
+                }
+            } else {
+                self.stack.pop();
+            }
+        }
     }
 }
 
 impl Default for Graph {
     fn default() -> Self {
-        Self {
-            cells: vec![Cell::Start, Cell::End],
-            links: HashMap::new(),
-        }
+        let mut graph = Self {
+            cells: vec![],
+            cell_ids: HashMap::new(),
+            links: vec![],
+            small_cave_indexes: vec![],
+        };
+        graph.cell_id(Cell::Start);
+        graph.cell_id(Cell::End);
+        graph
     }
 }
 
@@ -162,26 +302,40 @@ impl TryFrom<Vec<String>> for Graph {
 
         for line in &lines {
             let (cell1_str, cell2_str) = scan_fmt!(line, "{}-{}", String, String)?;
-            graph.register_link(cell1_str.parse()?, cell2_str.parse()?);
+            graph.register_link(cell1_str.parse()?, cell2_str.parse()?)?;
         }
 
         Ok(graph)
     }
 }
 
-fn part01(graph: &Graph) -> usize {
-    graph.find_all_paths(false).len()
+fn part01(graph: &Graph) -> anyhow::Result<u64> {
+    graph.count_paths(VisitPolicy::new(1, 1)?)
 }
 
-fn part02(graph: &Graph) -> usize {
-    graph.find_all_paths(true).len()
+fn part02(graph: &Graph) -> anyhow::Result<u64> {
+    graph.count_paths(VisitPolicy::new(1, 2)?)
 }
 
 fn main() -> anyhow::Result<()> {
-    let graph: Graph = Graph::try_from(input_lines(INPUT)?)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let graph: Graph = Graph::try_from(lines)?;
+
+    let policy = VisitPolicy::new(
+        opts.max_visits,
+        opts.max_single_visits.unwrap_or(opts.max_visits),
+    )?;
+    if opts.paths {
+        for path in graph.paths(policy)? {
+            println!("{}", graph.format_path(&path));
+        }
+    }
+    if opts.count {
+        println!("Paths ({}): {}", policy, graph.count_paths(policy)?);
+    }
 
-    println!("Part 1: {}", part01(&graph));
-    println!("Part 2: {}", part02(&graph));
+    println!("Part 1: {}", part01(&graph)?);
+    println!("Part 2: {}", part02(&graph)?);
 
     Ok(())
 }
@@ -1,17 +1,76 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::ocr::read_letters;
 use scan_fmt::scan_fmt;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::ops::RangeInclusive;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the sheet obtained after every fold
+    #[arg(long)]
+    show_folds: bool,
+
+    /// Prints, for every dot of the final sheet, the original dots that produced it
+    #[arg(long)]
+    provenance: bool,
+
+    /// Prints the positions of the original sheet that could have produced the final sheet
+    #[arg(long)]
+    unfold: bool,
+}
+
+type Position = (isize, isize);
+
 #[derive(Clone, Copy, Debug)]
 enum Fold {
     Horizontally(isize),
     Vertically(isize),
 }
 
+impl std::fmt::Display for Fold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Horizontally(value) => write!(f, "fold along x={}", value),
+            Self::Vertically(value) => write!(f, "fold along y={}", value),
+        }
+    }
+}
+
+impl Fold {
+    /// Position of a dot after the fold
+    fn apply(self, (row, column): Position) -> Position {
+        match self {
+            Self::Vertically(value) if row > value => (2 * value - row, column),
+            Self::Horizontally(value) if column > value => (row, 2 * value - column),
+            _ => (row, column),
+        }
+    }
+
+    /// Positions, before the fold, that would end up in the given position after the fold
+    fn unapply(self, (row, column): Position) -> Vec<Position> {
+        match self {
+            Self::Vertically(value) if row < value => {
+                vec![(row, column), (2 * value - row, column)]
+            }
+            Self::Horizontally(value) if column < value => {
+                vec![(row, column), (row, 2 * value - column)]
+            }
+            _ => vec![(row, column)],
+        }
+    }
+
+    fn is_on_fold_line(self, (row, column): Position) -> bool {
+        match self {
+            Self::Vertically(value) => row == value,
+            Self::Horizontally(value) => column == value,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 struct SparseGrid(HashMap<isize, HashSet<isize>>);
 
@@ -46,50 +105,190 @@ impl SparseGrid {
         min_columns..=max_columns
     }
 
-    fn cells(&self) -> impl Iterator<Item = (isize, isize)> + '_ {
+    fn cells(&self) -> impl Iterator<Item = Position> + '_ {
         self.0
             .iter()
             .flat_map(|(row, value)| value.iter().map(move |column| (*row, *column)))
     }
 
-    fn register(&mut self, row: isize, column: isize) {
+    fn contains(&self, (row, column): Position) -> bool {
         self.0
-            .entry(row)
-            .or_insert_with(HashSet::new)
-            .insert(column);
-    }
-
-    fn apply_folding_rules(&self, rules: &[Fold]) -> SparseGrid {
-        self.cells()
-            .filter_map(|(mut row, mut column)| {
-                for rule in rules {
-                    match rule {
-                        Fold::Vertically(value) if value == &row => {
-                            // The cell is on the folding line, so it should not be present
-                            return None;
-                        }
-                        Fold::Vertically(value) if &row > value => {
-                            row = 2 * value - row;
-                        }
-                        Fold::Horizontally(value) if value == &column => {
-                            // The cell is on the folding line, so it should not be present
-                            return None;
-                        }
-                        Fold::Horizontally(value) if &column > value => {
-                            column = 2 * value - column;
-                        }
-                        _ => {}
-                    }
-                }
-                Some((row, column))
-            })
-            .fold(SparseGrid::default(), |mut spare_grid, (row, column)| {
-                spare_grid.register(row, column);
-                spare_grid
+            .get(&row)
+            .is_some_and(|row_values| row_values.contains(&column))
+    }
+
+    fn register(&mut self, row: isize, column: isize) {
+        self.0.entry(row).or_default().insert(column);
+    }
+}
+
+impl FromIterator<Position> for SparseGrid {
+    fn from_iter<T: IntoIterator<Item = Position>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |mut sparse_grid, (row, column)| {
+                sparse_grid.register(row, column);
+                sparse_grid
             })
     }
 }
 
+#[derive(Debug)]
+enum FoldIssue {
+    /// The fold line is not in the middle of the sheet
+    Asymmetric { fold: Fold, size: isize },
+    /// Some dots are exactly on the fold line
+    DotsOnFoldLine { fold: Fold, dots: usize },
+}
+
+impl std::fmt::Display for FoldIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Asymmetric { fold, size } => write!(
+                f,
+                "{} is asymmetric: the sheet is {} long, so the midpoint is at {}",
+                fold,
+                size,
+                (size - 1) as f64 / 2.0
+            ),
+            Self::DotsOnFoldLine { fold, dots } => {
+                write!(f, "{} has {} dots on the fold line", fold, dots)
+            }
+        }
+    }
+}
+
+/// Sheet of transparent paper tracking, for each visible dot, the original dots that produced it.
+#[derive(Clone, Debug)]
+struct Sheet {
+    height: isize,
+    width: isize,
+    dots: HashMap<Position, BTreeSet<Position>>,
+}
+
+impl Sheet {
+    fn new(sparse_grid: &SparseGrid, folds: &[Fold]) -> Self {
+        let (mut height, mut width) = sparse_grid
+            .cells()
+            .fold((0, 0), |(height, width), (row, column)| {
+                (height.max(row + 1), width.max(column + 1))
+            });
+
+        // The size of the sheet is not part of the input, the first fold along each axis
+        // is assumed to be in the middle of the sheet
+        if let Some(value) = folds.iter().find_map(|fold| match fold {
+            Fold::Vertically(value) => Some(*value),
+            Fold::Horizontally(_) => None,
+        }) {
+            height = height.max(2 * value + 1);
+        }
+        if let Some(value) = folds.iter().find_map(|fold| match fold {
+            Fold::Horizontally(value) => Some(*value),
+            Fold::Vertically(_) => None,
+        }) {
+            width = width.max(2 * value + 1);
+        }
+
+        Self {
+            height,
+            width,
+            dots: sparse_grid
+                .cells()
+                .map(|position| (position, BTreeSet::from([position])))
+                .collect(),
+        }
+    }
+
+    fn fold(&self, fold: Fold) -> (Self, Vec<FoldIssue>) {
+        let mut issues = Vec::new();
+
+        let (size, value) = match fold {
+            Fold::Vertically(value) => (self.height, value),
+            Fold::Horizontally(value) => (self.width, value),
+        };
+        if size != 2 * value + 1 {
+            issues.push(FoldIssue::Asymmetric { fold, size });
+        }
+
+        let dots_on_fold_line = self
+            .dots
+            .keys()
+            .filter(|position| fold.is_on_fold_line(**position))
+            .count();
+        if dots_on_fold_line > 0 {
+            issues.push(FoldIssue::DotsOnFoldLine {
+                fold,
+                dots: dots_on_fold_line,
+            });
+        }
+
+        let mut dots: HashMap<Position, BTreeSet<Position>> = HashMap::new();
+        for (position, origins) in &self.dots {
+            dots.entry(fold.apply(*position))
+                .or_default()
+                .extend(origins.iter().copied());
+        }
+
+        let (height, width) = match fold {
+            Fold::Vertically(value) => (value, self.width),
+            Fold::Horizontally(value) => (self.height, value),
+        };
+        (
+            Self {
+                height,
+                width,
+                dots,
+            },
+            issues,
+        )
+    }
+
+    fn sparse_grid(&self) -> SparseGrid {
+        self.dots.keys().copied().collect()
+    }
+
+    /// Original dots that produced the dot in the given position
+    fn provenance(&self, position: Position) -> Option<&BTreeSet<Position>> {
+        self.dots.get(&position)
+    }
+}
+
+impl std::fmt::Display for Sheet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Asymmetric folds might move dots outside of the sheet boundaries
+        let (min_row, min_column, max_row, max_column) = self.dots.keys().fold(
+            (0, 0, self.height - 1, self.width - 1),
+            |(min_row, min_column, max_row, max_column), (row, column)| {
+                (
+                    min_row.min(*row),
+                    min_column.min(*column),
+                    max_row.max(*row),
+                    max_column.max(*column),
+                )
+            },
+        );
+
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                if self.dots.contains_key(&(row, column)) {
+                    write!(f, "#")?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of applying a fold to the sheet
+#[derive(Debug)]
+struct FoldStep {
+    fold: Fold,
+    sheet: Sheet,
+    issues: Vec<FoldIssue>,
+}
+
 #[derive(Debug)]
 struct Origami {
     sparse_grid: SparseGrid,
@@ -97,16 +296,37 @@ struct Origami {
 }
 
 impl Origami {
-    fn apply_first_move(&self) -> SparseGrid {
-        self.sparse_grid.apply_folding_rules(&[self
-            .rules
-            .first()
-            .copied()
-            .expect("At least one rule is defined")])
+    /// Applies all the folds in order, reporting the sheet obtained after each of them
+    fn fold_history(&self) -> Vec<FoldStep> {
+        let mut sheet = Sheet::new(&self.sparse_grid, &self.rules);
+        self.rules
+            .iter()
+            .map(|fold| {
+                let (next_sheet, issues) = sheet.fold(*fold);
+                sheet = next_sheet;
+                FoldStep {
+                    fold: *fold,
+                    sheet: sheet.clone(),
+                    issues,
+                }
+            })
+            .collect()
     }
 
-    fn apply_all_moves(&self) -> SparseGrid {
-        self.sparse_grid.apply_folding_rules(&self.rules)
+    /// Unfolds the given grid, obtained by applying all the folds, into the original sheet.
+    ///
+    /// As folding is not reversible, the result contains all the positions that could have
+    /// produced a dot in the folded grid. Each candidate original sheet is a subset of it.
+    fn unfold(&self, folded: &SparseGrid) -> SparseGrid {
+        self.rules
+            .iter()
+            .rev()
+            .fold(folded.clone(), |sparse_grid, fold| {
+                sparse_grid
+                    .cells()
+                    .flat_map(|position| fold.unapply(position))
+                    .collect()
+            })
     }
 }
 
@@ -137,25 +357,28 @@ impl TryFrom<Vec<String>> for Origami {
                 sparse_grid.register(row, column);
             }
         }
+        anyhow::ensure!(!rules.is_empty(), "At least one fold is expected");
         Ok(Self { sparse_grid, rules })
     }
 }
 
-fn part01(origami: &Origami) -> usize {
-    origami.apply_first_move().cells().count()
+fn part01(history: &[FoldStep]) -> usize {
+    history[0].sheet.dots.len()
 }
 
-fn part02(origami: &Origami) -> String {
-    format!("\n{}", origami.apply_all_moves())
+fn part02(history: &[FoldStep]) -> String {
+    let sparse_grid = history[history.len() - 1].sheet.sparse_grid();
+    read_letters(sparse_grid.cells()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        format!("\n{}", sparse_grid)
+    })
 }
 
 impl std::fmt::Display for SparseGrid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for row_index in self.rows_range() {
-            let maybe_row_values = self.0.get(&row_index);
-
             for column_index in self.columns_range() {
-                if maybe_row_values.map_or(false, |row_values| row_values.contains(&column_index)) {
+                if self.contains((row_index, column_index)) {
                     write!(f, "#")?;
                 } else {
                     write!(f, " ")?;
@@ -168,10 +391,46 @@ impl std::fmt::Display for SparseGrid {
 }
 
 fn main() -> anyhow::Result<()> {
-    let origami = Origami::try_from(input_lines(INPUT)?)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let origami = Origami::try_from(lines)?;
+    let history = origami.fold_history();
+
+    for step in &history {
+        if opts.show_folds {
+            println!("{}\n{}", step.fold, step.sheet);
+        }
+        for issue in &step.issues {
+            eprintln!("Warning: {}", issue);
+        }
+    }
+
+    let last_sheet = &history[history.len() - 1].sheet;
+    if opts.provenance {
+        let mut positions: Vec<_> = last_sheet.dots.keys().copied().collect();
+        positions.sort_unstable();
+        for (row, column) in positions {
+            if let Some(origins) = last_sheet.provenance((row, column)) {
+                let origins = origins
+                    .iter()
+                    .map(|(row, column)| format!("{},{}", column, row))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!("{},{} <- {}", column, row, origins);
+            }
+        }
+    }
+    if opts.unfold {
+        let unfolded = origami.unfold(&last_sheet.sparse_grid());
+        println!(
+            "Unfolded sheet has {} candidate dots (originally {} dots)\n{}",
+            unfolded.cells().count(),
+            origami.sparse_grid.cells().count(),
+            unfolded
+        );
+    }
 
-    println!("Part 1: {}", part01(&origami));
-    println!("Part 2: {}", part02(&origami));
+    println!("Part 1: {}", part01(&history));
+    println!("Part 2: {}", part02(&history));
 
     Ok(())
 }
@@ -1,126 +1,307 @@
-use helpers::input_lines;
-use std::collections::HashMap;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::transition::TransitionMatrix;
+use num_bigint::BigUint;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::convert::TryFrom;
-use std::mem::swap;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Number of insertion steps used by `--histograms` and `--char-at`
+    #[arg(long, default_value_t = 10)]
+    steps: usize,
+
+    /// Prints the occurrences of each element after every step
+    #[arg(long)]
+    histograms: bool,
+
+    /// Prints the element at the given index (starting from 0) of the polymer
+    #[arg(long)]
+    char_at: Option<u128>,
+}
+
+type Histogram = BTreeMap<char, BigUint>;
+
+/// Parses `source -> insertion`, where source is a pair of elements and insertion
+/// is a non empty sequence of elements
+fn parse_insertion_rule(line: &str) -> Result<((char, char), Vec<char>), anyhow::Error> {
+    if let Some((source, destination)) = line.split_once(" -> ") {
+        let source_chars: Vec<char> = source.chars().collect();
+        anyhow::ensure!(
+            source_chars.len() == 2,
+            "source ({source}) is expected to have only 2 elements",
+            source = source
+        );
+        let insertion: Vec<char> = destination.chars().collect();
+        anyhow::ensure!(
+            !insertion.is_empty(),
+            "destination of {source} is expected to have at least 1 element",
+            source = source
+        );
+        Ok(((source_chars[0], source_chars[1]), insertion))
+    } else {
+        Err(anyhow::anyhow!(
+            "'{line}' does not respect the expected structure 'source -> destination'",
+            line = line
+        ))
+    }
+}
+
 #[derive(Debug)]
-struct Input {
+struct Polymer {
     template: Vec<char>,
-    insertion_rules: HashMap<(char, char), char>,
+    /// Pairs that can appear in the polymer
+    pairs: Vec<(char, char)>,
+    pair_indexes: HashMap<(char, char), usize>,
+    /// Pairs (in order) replacing each pair after an insertion step
+    children: Vec<Vec<usize>>,
+    /// Memoized number of elements generated by each pair after a given number of steps,
+    /// excluding the last element of the pair (so that lengths of consecutive pairs add up).
+    /// Lengths saturate at `u128::MAX`.
+    lengths: Vec<Vec<u128>>,
 }
 
-impl TryFrom<Vec<String>> for Input {
+impl TryFrom<Vec<String>> for Polymer {
     type Error = anyhow::Error;
 
     fn try_from(lines: Vec<String>) -> Result<Self, Self::Error> {
         anyhow::ensure!(lines.len() > 2);
-        let template = lines[0].chars().collect();
-        let insertion_rules = lines[2..]
-            .iter()
-            .map(|line| {
-                if let Some((source, destination)) = line.split_once(" -> ") {
-                    let bytes_source: Vec<char> = source.chars().collect();
-                    anyhow::ensure!(
-                        bytes_source.len() == 2,
-                        "source ({source}) is expected to have only 2 elements",
-                        source = source
-                    );
-                    let bytes_destination: Vec<char> = destination.chars().collect();
-                    anyhow::ensure!(
-                        destination.len() == 1,
-                        "destination ({destination}) is expected to have only 2 elements",
-                        destination = destination
-                    );
-                    Ok(((bytes_source[0], bytes_source[1]), bytes_destination[0]))
-                } else {
-                    Err(anyhow::anyhow!(
-                        "'{line}' does not respect the expected structure 'source -> destination'",
-                        line = line
-                    ))
+        let template: Vec<char> = lines[0].chars().collect();
+        anyhow::ensure!(!template.is_empty(), "Empty polymer template");
+
+        let mut insertion_rules: HashMap<(char, char), Vec<char>> = HashMap::new();
+        for line in &lines[2..] {
+            let (source, insertion) = parse_insertion_rule(line)?;
+            if let Some(other_insertion) = insertion_rules.get(&source) {
+                anyhow::ensure!(
+                    *other_insertion == insertion,
+                    "Conflicting rules for {a}{b}: {first} and {second}",
+                    a = source.0,
+                    b = source.1,
+                    first = other_insertion.iter().collect::<String>(),
+                    second = insertion.iter().collect::<String>(),
+                );
+            }
+            insertion_rules.insert(source, insertion);
+        }
+
+        // Explore the pairs reachable from the template
+        let mut pairs = vec![];
+        let mut pair_indexes = HashMap::new();
+        let mut queue: VecDeque<(char, char)> = VecDeque::new();
+        let mut missing_rules = BTreeSet::new();
+        let mut children_pairs = vec![];
+        for pair in template.windows(2) {
+            queue.push_back((pair[0], pair[1]));
+        }
+        while let Some(pair) = queue.pop_front() {
+            if pair_indexes.contains_key(&pair) {
+                continue;
+            }
+            pair_indexes.insert(pair, pairs.len());
+            pairs.push(pair);
+            let expanded: Vec<char> = match insertion_rules.get(&pair) {
+                Some(insertion) => std::iter::once(pair.0)
+                    .chain(insertion.iter().copied())
+                    .chain(std::iter::once(pair.1))
+                    .collect(),
+                None => {
+                    missing_rules.insert(format!("{}{}", pair.0, pair.1));
+                    vec![pair.0, pair.1]
                 }
-            })
-            .collect::<Result<HashMap<(char, char), char>, _>>()?;
+            };
+            let children: Vec<(char, char)> = expanded
+                .windows(2)
+                .map(|child| (child[0], child[1]))
+                .collect();
+            queue.extend(children.iter().copied());
+            children_pairs.push(children);
+        }
+        anyhow::ensure!(
+            missing_rules.is_empty(),
+            "Missing insertion rules for {missing_rules:?}",
+            missing_rules = missing_rules
+        );
+
+        let children = children_pairs
+            .iter()
+            .map(|children| children.iter().map(|child| pair_indexes[child]).collect())
+            .collect();
+        let lengths = vec![vec![1; pairs.len()]];
         Ok(Self {
             template,
-            insertion_rules,
+            pairs,
+            pair_indexes,
+            children,
+            lengths,
         })
     }
 }
 
-impl Input {
-    fn run_iterations(&self, iteration_count: usize) -> HashMap<(char, char), usize> {
-        // Running for real all the iterations would be "easy" from an implementiation
-        // prespective but orrible from a memory prespective as the memory needed to
-        // keep the updating base (elements) would double at every iteration leading
-        // to O(KB) in Part 1 and to O(TB) in Part 2
-        // In order to make it scalable we are keeping track of the count of the different
-        // pairs (we cannot have more than 26*26=676 pairs)
-
-        let mut counters = self.template.windows(2).fold(
-            HashMap::default(),
-            |mut counters: HashMap<_, _>, pair| {
-                *counters.entry((pair[0], pair[1])).or_default() += 1;
-                counters
-            },
-        );
-        let mut tmp_counters = HashMap::default();
-
-        for _ in 0..iteration_count {
-            tmp_counters.clear();
-            for (pair, count) in &counters {
-                if let Some(insertion) = self.insertion_rules.get(pair) {
-                    *tmp_counters.entry((pair.0, *insertion)).or_default() += count;
-                    *tmp_counters.entry((*insertion, pair.1)).or_default() += count;
-                }
+impl Polymer {
+    fn transition_matrix(&self) -> TransitionMatrix {
+        let mut transition_matrix = TransitionMatrix::zeros(self.pairs.len());
+        for (pair, children) in self.children.iter().enumerate() {
+            for child in children {
+                transition_matrix.add(pair, *child, 1);
             }
-            // Using swap instead of copying the elements to avoid copying
-            // all the elements all the time but rather we flip the "pointers"
-            swap(&mut counters, &mut tmp_counters);
         }
+        transition_matrix
+    }
 
+    fn initial_pair_counts(&self) -> Vec<BigUint> {
+        let mut counters = vec![BigUint::default(); self.pairs.len()];
+        for pair in self.template.windows(2) {
+            counters[self.pair_indexes[&(pair[0], pair[1])]] += 1_u8;
+        }
         counters
     }
 
-    fn occurrences_after_iterations(&self, iteration_count: usize) -> HashMap<char, usize> {
-        let mut result = HashMap::new();
+    fn histogram(&self, pair_counts: &[BigUint]) -> Histogram {
+        let mut result = Histogram::new();
         result.insert(
             *self
                 .template
                 .last()
-                .expect("Initial template has more than 1 element"),
-            1,
+                .expect("Initial template has at least 1 element"),
+            BigUint::from(1_u8),
         );
-        for (pair, count) in self.run_iterations(iteration_count) {
+        for (pair, count) in self.pairs.iter().zip(pair_counts) {
             *result.entry(pair.0).or_default() += count;
         }
+        result.retain(|_, count| *count != BigUint::default());
         result
     }
-}
 
-fn part01(input: &Input) -> usize {
-    let final_occurrences = input.occurrences_after_iterations(10);
+    fn occurrences_after_iterations(&self, iteration_count: usize) -> Histogram {
+        // Running for real all the iterations would be "easy" from an implementiation
+        // prespective but orrible from a memory prespective as the memory needed to
+        // keep the updating base (elements) would double at every iteration leading
+        // to O(KB) in Part 1 and to O(TB) in Part 2
+        // In order to make it scalable we are keeping track of the count of the different
+        // pairs (we cannot have more than 26*26=676 pairs), which evolve as a linear model
+        let pair_counts = self
+            .transition_matrix()
+            .state_after(&self.initial_pair_counts(), iteration_count as u64);
+        self.histogram(&pair_counts)
+    }
+
+    /// Histograms of the polymer before the insertions and after each step
+    fn histograms(&self, iteration_count: usize) -> Vec<Histogram> {
+        let transition_matrix = self.transition_matrix();
+        let mut pair_counts = self.initial_pair_counts();
+        let mut histograms = vec![self.histogram(&pair_counts)];
+        for _ in 0..iteration_count {
+            pair_counts = transition_matrix.apply(&pair_counts);
+            histograms.push(self.histogram(&pair_counts));
+        }
+        histograms
+    }
+
+    fn ensure_lengths(&mut self, iteration_count: usize) {
+        while self.lengths.len() <= iteration_count {
+            let previous = &self.lengths[self.lengths.len() - 1];
+            let next = self
+                .children
+                .iter()
+                .map(|children| {
+                    children.iter().fold(0_u128, |length, child| {
+                        length.saturating_add(previous[*child])
+                    })
+                })
+                .collect();
+            self.lengths.push(next);
+        }
+    }
 
-    let min = final_occurrences.values().min().unwrap_or(&0);
-    let max = final_occurrences.values().max().unwrap_or(&usize::MAX);
+    /// Length of the polymer (saturated at `u128::MAX`)
+    fn length_after_iterations(&mut self, iteration_count: usize) -> u128 {
+        self.ensure_lengths(iteration_count);
+        self.template
+            .windows(2)
+            .map(|pair| self.lengths[iteration_count][self.pair_indexes[&(pair[0], pair[1])]])
+            .fold(1, u128::saturating_add)
+    }
 
-    max - min
-}
+    /// Element at `index` of the polymer, without building the polymer
+    fn char_at(&mut self, iteration_count: usize, mut index: u128) -> Option<char> {
+        self.ensure_lengths(iteration_count);
+        for pair in self.template.windows(2) {
+            let mut pair_index = self.pair_indexes[&(pair[0], pair[1])];
+            let length = self.lengths[iteration_count][pair_index];
+            if index >= length {
+                index -= length;
+                continue;
+            }
 
-fn part02(input: &Input) -> usize {
-    let final_occurrences = input.occurrences_after_iterations(40);
+            // Descend into the pair generating the element, one step at a time
+            for step in (0..iteration_count).rev() {
+                for child in &self.children[pair_index] {
+                    let child_length = self.lengths[step][*child];
+                    if index < child_length {
+                        pair_index = *child;
+                        break;
+                    }
+                    index -= child_length;
+                }
+            }
+            return Some(self.pairs[pair_index].0);
+        }
+        if index == 0 {
+            self.template.last().copied()
+        } else {
+            None
+        }
+    }
+}
 
-    let min = final_occurrences.values().min().unwrap_or(&0);
-    let max = final_occurrences.values().max().unwrap_or(&usize::MAX);
+fn most_minus_least_common(histogram: &Histogram) -> BigUint {
+    let min = histogram.values().min().cloned().unwrap_or_default();
+    let max = histogram.values().max().cloned().unwrap_or_default();
 
     max - min
 }
 
+fn part01(polymer: &Polymer) -> BigUint {
+    most_minus_least_common(&polymer.occurrences_after_iterations(10))
+}
+
+fn part02(polymer: &Polymer) -> BigUint {
+    most_minus_least_common(&polymer.occurrences_after_iterations(40))
+}
+
 fn main() -> anyhow::Result<()> {
-    let input = Input::try_from(input_lines(INPUT)?)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let mut polymer = Polymer::try_from(lines)?;
+
+    if opts.histograms {
+        for (step, histogram) in polymer.histograms(opts.steps).iter().enumerate() {
+            let counts: Vec<String> = histogram
+                .iter()
+                .map(|(element, count)| format!("{}={}", element, count))
+                .collect();
+            println!("Step {}: {}", step, counts.join(", "));
+        }
+    }
+    if let Some(index) = opts.char_at {
+        let length = polymer.length_after_iterations(opts.steps);
+        match polymer.char_at(opts.steps, index) {
+            Some(element) => println!(
+                "Element {} after {} steps: {} (length {})",
+                index, opts.steps, element, length
+            ),
+            None => anyhow::bail!(
+                "Index {} is out of the polymer after {} steps (length {})",
+                index,
+                opts.steps,
+                length
+            ),
+        }
+    }
 
-    println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    println!("Part 1: {}", part01(&polymer));
+    println!("Part 2: {}", part02(&polymer));
     Ok(())
 }
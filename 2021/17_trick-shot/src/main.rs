@@ -1,10 +1,19 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
+use std::collections::BTreeSet;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the trajectory of the probe launched with the given initial speed (formatted as `x,y`)
+    #[arg(long, allow_hyphen_values = true)]
+    trajectory: Option<String>,
+}
+
 #[derive(Debug)]
 struct Area {
     min_x: isize,
@@ -37,96 +46,180 @@ impl FromStr for Area {
     }
 }
 
-impl Area {
-    fn possible_horizontal_initial_speed(&self) -> RangeInclusive<isize> {
-        match (self.min_x, self.max_x) {
-            (min_x, max_x) if min_x.is_negative() && max_x.is_negative() => min_x..=0,
-            (min_x, max_x) if min_x.is_positive() && max_x.is_positive() => 0..=max_x,
-            (min_x, max_x) => min_x..=max_x,
-        }
+/// Sum of the integers in `1..=n` (distance travelled by a coordinate subject to drag before stopping)
+fn triangular(n: isize) -> isize {
+    n * (n + 1) / 2
+}
+
+/// Smallest non-negative speed whose travelled distance, before stopping, is at least `distance`
+fn min_speed_to_travel(distance: isize) -> isize {
+    let mut speed = (((8 * distance + 1) as f64).sqrt() as isize - 1) / 2;
+    while triangular(speed) < distance {
+        speed += 1;
+    }
+    while speed > 0 && triangular(speed - 1) >= distance {
+        speed -= 1;
     }
+    speed
+}
 
-    fn possible_vertical_initial_speed(&self) -> RangeInclusive<isize> {
-        // This is an hack as I haven't thought about how to
-        // be smart and restrict the range
-        const MAX_VERTICAL_SPEED: isize = 1000;
+/// Position of the probe after `step` steps, launched with the given initial speed
+fn position(speed_x: isize, speed_y: isize, step: isize) -> (isize, isize) {
+    let moving_steps = step.min(speed_x.abs());
+    let x = speed_x.signum() * (speed_x.abs() * moving_steps - triangular(moving_steps - 1));
+    let y = speed_y * step - triangular(step - 1);
+    (x, y)
+}
 
-        match (self.min_y, self.max_y) {
-            (min_y, max_y) if min_y.is_negative() && max_y.is_negative() => {
-                min_y..=MAX_VERTICAL_SPEED
-            }
-            (min_y, max_y) if min_y.is_positive() && max_y.is_positive() => {
-                0..=max_y.max(MAX_VERTICAL_SPEED)
-            }
-            (min_y, max_y) => min_y..=max_y,
+impl Area {
+    fn contains(&self, (x, y): (isize, isize)) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+
+    fn possible_horizontal_initial_speed(&self) -> RangeInclusive<isize> {
+        if self.min_x > 0 {
+            min_speed_to_travel(self.min_x)..=self.max_x
+        } else if self.max_x < 0 {
+            self.min_x..=-min_speed_to_travel(-self.max_x)
+        } else {
+            self.min_x..=self.max_x
         }
     }
 
-    fn max_vertical_positition_toward_target_area(
-        &self,
-        mut speed_x: isize,
-        mut speed_y: isize,
-    ) -> Option<isize> {
-        let (mut x, mut y) = (0, 0);
-        let mut max_y = isize::MIN;
+    /// Horizontal speeds for which the probe stops within the horizontal bounds of the area
+    fn stopping_horizontal_speeds(&self) -> impl Iterator<Item = isize> + '_ {
+        self.possible_horizontal_initial_speed().filter(|speed_x| {
+            (self.min_x..=self.max_x).contains(&(speed_x.signum() * triangular(speed_x.abs())))
+        })
+    }
 
-        loop {
-            x += speed_x;
-            y += speed_y;
+    /// Range of vertical speeds for which the probe is within the vertical bounds of the area after `step` steps
+    fn vertical_initial_speed_at_step(&self, step: isize) -> RangeInclusive<isize> {
+        // y(step) = speed_y * step - triangular(step - 1) is linear in speed_y
+        let drag = triangular(step - 1);
+        let min_speed_y = (self.min_y + drag).div_euclid(step)
+            + isize::from((self.min_y + drag).rem_euclid(step) != 0);
+        let max_speed_y = (self.max_y + drag).div_euclid(step);
+        min_speed_y..=max_speed_y
+    }
 
-            max_y = max_y.max(y);
+    /// Last step after which the probe could still be within the area
+    fn max_step(&self) -> anyhow::Result<isize> {
+        if self.min_y > 0 || self.max_y < 0 {
+            // An integer speed reaching the area exists only if |y| >= step / 2
+            Ok(2 * self.min_y.abs().max(self.max_y.abs()) + 1)
+        } else if let Some(speed_x) = self.stopping_horizontal_speeds().next() {
+            Err(anyhow::anyhow!(
+                "Infinite initial speeds reach the area: the probe launched with horizontal speed {} stops within the area and any upward speed brings it back to y=0",
+                speed_x
+            ))
+        } else {
+            let range = self.possible_horizontal_initial_speed();
+            Ok(range.start().abs().max(range.end().abs()))
+        }
+    }
 
-            if speed_x.is_positive() {
-                speed_x -= 1;
-            } else if speed_x.is_negative() {
-                speed_x += 1;
+    /// All the initial speeds for which the probe is within the area after any step
+    fn initial_speeds(&self) -> anyhow::Result<BTreeSet<(isize, isize)>> {
+        let mut initial_speeds = BTreeSet::new();
+        for step in 1..=self.max_step()? {
+            let speeds_y = self.vertical_initial_speed_at_step(step);
+            if speeds_y.is_empty() {
+                continue;
+            }
+            for speed_x in self.possible_horizontal_initial_speed() {
+                let (x, _) = position(speed_x, 0, step);
+                if (self.min_x..=self.max_x).contains(&x) {
+                    initial_speeds.extend(speeds_y.clone().map(|speed_y| (speed_x, speed_y)));
+                }
             }
-            speed_y -= 1;
+        }
+        Ok(initial_speeds)
+    }
 
-            if x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y {
-                return Some(max_y);
+    /// Positions of the probe until it reaches the area, or until it can no longer reach it
+    fn trajectory(&self, speed_x: isize, speed_y: isize) -> Vec<(isize, isize)> {
+        let mut trajectory = vec![];
+        for step in 1.. {
+            let (x, y) = position(speed_x, speed_y, step);
+            trajectory.push((x, y));
+            let falling = speed_y - step < 0;
+            let stopped = step >= speed_x.abs();
+            let beyond_x = (speed_x > 0 && x > self.max_x)
+                || (speed_x < 0 && x < self.min_x)
+                || (stopped && !(self.min_x..=self.max_x).contains(&x));
+            if self.contains((x, y)) || (falling && y < self.min_y) || beyond_x {
+                break;
             }
+        }
+        trajectory
+    }
 
-            if x > self.max_x || y < self.min_y {
-                return None;
+    fn render_trajectory(&self, trajectory: &[(isize, isize)]) -> String {
+        let positions: BTreeSet<(isize, isize)> = trajectory.iter().copied().collect();
+        let corners = [(0, 0), (self.min_x, self.min_y), (self.max_x, self.max_y)];
+        let bounds = trajectory.iter().chain(&corners);
+        let min_x = bounds.clone().map(|(x, _)| *x).min().unwrap_or(0);
+        let max_x = bounds.clone().map(|(x, _)| *x).max().unwrap_or(0);
+        let min_y = bounds.clone().map(|(_, y)| *y).min().unwrap_or(0);
+        let max_y = bounds.map(|(_, y)| *y).max().unwrap_or(0);
+
+        let mut rendered = String::new();
+        for y in (min_y..=max_y).rev() {
+            for x in min_x..=max_x {
+                rendered.push(if (x, y) == (0, 0) {
+                    'S'
+                } else if positions.contains(&(x, y)) {
+                    '#'
+                } else if self.contains((x, y)) {
+                    'T'
+                } else {
+                    '.'
+                });
             }
+            rendered.push('\n');
         }
+        rendered
     }
+}
 
-    fn can_reach_target_area(&self, speed_x: isize, speed_y: isize) -> bool {
-        self.max_vertical_positition_toward_target_area(speed_x, speed_y)
-            .is_some()
+/// Highest vertical position reached by the probe launched with the given vertical speed
+fn max_height(speed_y: isize) -> isize {
+    if speed_y > 0 {
+        triangular(speed_y)
+    } else {
+        0
     }
 }
 
-fn part01(area: &Area) -> isize {
-    area.possible_horizontal_initial_speed()
-        .flat_map(|speed_x| {
-            area.possible_vertical_initial_speed()
-                .filter_map(move |speed_y| {
-                    area.max_vertical_positition_toward_target_area(speed_x, speed_y)
-                })
-        })
+fn part01(initial_speeds: &BTreeSet<(isize, isize)>) -> isize {
+    initial_speeds
+        .iter()
+        .map(|(_, speed_y)| max_height(*speed_y))
         .max()
         .expect("At least one combination should be able to reach the destination")
 }
 
-fn part02(area: &Area) -> usize {
-    area.possible_horizontal_initial_speed()
-        .flat_map(|speed_x| {
-            area.possible_vertical_initial_speed()
-                .filter(move |speed_y| area.can_reach_target_area(speed_x, *speed_y))
-        })
-        .count()
+fn part02(initial_speeds: &BTreeSet<(isize, isize)>) -> usize {
+    initial_speeds.len()
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
     anyhow::ensure!(lines.len() == 1);
     let area: Area = lines[0].parse()?;
 
-    println!("Part 1: {}", part01(&area));
-    println!("Part 2: {}", part02(&area));
+    if let Some(trajectory) = opts.trajectory {
+        let (speed_x, speed_y) = scan_fmt!(&trajectory, "{},{}", isize, isize)?;
+        print!(
+            "{}",
+            area.render_trajectory(&area.trajectory(speed_x, speed_y))
+        );
+    }
+
+    let initial_speeds = area.initial_speeds()?;
+    println!("Part 1: {}", part01(&initial_speeds));
+    println!("Part 2: {}", part02(&initial_speeds));
 
     Ok(())
 }
@@ -1,16 +1,19 @@
-use helpers::input_lines;
-use std::collections::HashSet;
-use std::convert::{TryFrom, TryInto};
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-const ALGORITHM_LENGTH: usize = 512;
 const INPUT: &str = include_str!("../input.txt");
+const WORD_BITS: usize = u64::BITS as usize;
 
-#[inline]
-fn cast_to_isize(value: usize) -> isize {
-    value
-        .try_into()
-        .expect("Expected to be able to convert value")
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Directory where the image obtained after each enhancement is exported as PBM
+    #[arg(long)]
+    pbm_dir: Option<PathBuf>,
 }
 
 #[inline]
@@ -25,112 +28,121 @@ fn is_light_color(pixel_color: u8) -> Result<bool, anyhow::Error> {
     }
 }
 
-#[derive(Debug)]
-struct ImageEnhancementAlgorithm {
-    light_positions: HashSet<usize>,
+/// Bit-packed sequence of booleans
+#[derive(Clone, Debug, Default)]
+struct BitVec {
+    words: Vec<u64>,
 }
 
-impl FromStr for ImageEnhancementAlgorithm {
-    type Err = anyhow::Error;
+impl BitVec {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+        }
+    }
 
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let pixel_colors = line.as_bytes();
-        anyhow::ensure!(pixel_colors.len() == ALGORITHM_LENGTH);
+    fn get(&self, index: usize) -> bool {
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
 
-        let light_positions: HashSet<usize> = pixel_colors
+    fn set(&mut self, index: usize) {
+        self.words[index / WORD_BITS] |= 1 << (index % WORD_BITS);
+    }
+
+    fn count_ones(&self) -> usize {
+        self.words
             .iter()
-            .enumerate()
-            .filter_map(|(index, pixel_color)| match is_light_color(*pixel_color) {
-                Ok(true) => Some(Ok(index)),
-                Ok(false) => None,
-                Err(err) => Some(Err(err)),
-            })
-            .collect::<Result<_, _>>()?;
-
-        Ok(Self { light_positions })
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
 }
 
+/// Rule table mapping each NxN square of pixels to the color of the central pixel.
+///
+/// A table with `2^(N*N)` entries defines the kernel size `N` (which has to be odd).
 #[derive(Debug)]
-struct Square {
-    value: usize,
+struct ImageEnhancementAlgorithm {
+    kernel_size: usize,
+    light_positions: BitVec,
 }
 
-impl From<[[bool; 3]; 3]> for Square {
-    fn from(values: [[bool; 3]; 3]) -> Self {
-        let value = (u16::from(values[0][0]) << 8)
-            | (u16::from(values[0][1]) << 7)
-            | (u16::from(values[0][2]) << 6)
-            | (u16::from(values[1][0]) << 5)
-            | (u16::from(values[1][1]) << 4)
-            | (u16::from(values[1][2]) << 3)
-            | (u16::from(values[2][0]) << 2)
-            | (u16::from(values[2][1]) << 1)
-            | u16::from(values[2][2]);
+impl FromStr for ImageEnhancementAlgorithm {
+    type Err = anyhow::Error;
 
-        Self {
-            value: value as usize,
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let pixel_colors = line.as_bytes();
+        let kernel_size = (1..)
+            .step_by(2)
+            .take_while(|kernel_size: &usize| kernel_size * kernel_size < WORD_BITS)
+            .find(|kernel_size| 1 << (kernel_size * kernel_size) == pixel_colors.len())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The algorithm has {} entries, expected 2^(N*N) entries for an odd kernel size N",
+                    pixel_colors.len()
+                )
+            })?;
+
+        let mut light_positions = BitVec::new(pixel_colors.len());
+        for (index, pixel_color) in pixel_colors.iter().enumerate() {
+            if is_light_color(*pixel_color)? {
+                light_positions.set(index);
+            }
         }
+
+        Ok(Self {
+            kernel_size,
+            light_positions,
+        })
     }
 }
 
-#[derive(Clone, Debug)]
-struct Ranges {
-    min_row: isize,
-    max_row: isize,
-    min_column: isize,
-    max_column: isize,
-}
+impl ImageEnhancementAlgorithm {
+    fn is_light(&self, position: usize) -> bool {
+        self.light_positions.get(position)
+    }
 
-impl Default for Ranges {
-    fn default() -> Self {
-        Self {
-            min_row: isize::MAX,
-            max_row: isize::MIN,
-            min_column: isize::MAX,
-            max_column: isize::MIN,
+    /// Color of the infinite background after the enhancement
+    fn next_background(&self, is_light_background: bool) -> bool {
+        if is_light_background {
+            self.is_light((1 << (self.kernel_size * self.kernel_size)) - 1)
+        } else {
+            self.is_light(0)
         }
     }
 }
 
+/// Dense image on an infinite canvas: pixels outside of the bounding box have the background color
 #[derive(Clone, Debug)]
 struct Image {
-    light_positions: HashSet<(isize, isize)>,
-    ranges: Ranges,
-    is_light_outside_ranges: bool,
+    height: usize,
+    width: usize,
+    /// Coordinates of the top-left pixel of the bounding box
+    top: isize,
+    left: isize,
+    light_pixels: BitVec,
+    is_light_background: bool,
 }
 
 impl TryFrom<&[String]> for Image {
     type Error = anyhow::Error;
 
     fn try_from(lines: &[String]) -> Result<Self, Self::Error> {
-        anyhow::ensure!(lines.len() > 0);
-        let light_positions = lines
-            .iter()
-            .enumerate()
-            .flat_map(|(line_number, line)| {
-                line.bytes()
-                    .enumerate()
-                    .filter_map(move |(column_number, pixel_color)| {
-                        match is_light_color(pixel_color) {
-                            Ok(true) => Some(Ok((
-                                cast_to_isize(line_number),
-                                cast_to_isize(column_number),
-                            ))),
-                            Ok(false) => None,
-                            Err(err) => Some(Err(err)),
-                        }
-                    })
-            })
-            .collect::<Result<_, _>>()?;
-
-        let mut res = Self {
-            light_positions,
-            ranges: Ranges::default(),
-            is_light_outside_ranges: false,
-        };
-        res.update_row_column_ranges();
-        Ok(res)
+        anyhow::ensure!(!lines.is_empty());
+        let width = lines[0].len();
+        anyhow::ensure!(
+            lines.iter().all(|line| line.len() == width),
+            "All the lines of the image are expected to have the same length"
+        );
+
+        let mut image = Self::new(lines.len(), width, 0, 0, false);
+        for (row, line) in lines.iter().enumerate() {
+            for (column, pixel_color) in line.bytes().enumerate() {
+                if is_light_color(pixel_color)? {
+                    image.light_pixels.set(row * width + column);
+                }
+            }
+        }
+        Ok(image)
     }
 }
 
@@ -152,123 +164,114 @@ impl TryFrom<Vec<String>> for Input {
     }
 }
 
-impl ImageEnhancementAlgorithm {
-    fn is_light(&self, position: usize) -> bool {
-        debug_assert!(position < 512);
-        self.light_positions.contains(&position)
-    }
-}
-
-impl Square {
-    fn to_value(&self) -> usize {
-        self.value as usize
-    }
-}
-
 impl Image {
-    fn update_row_column_ranges(&mut self) {
-        self.ranges = self.light_positions.iter().fold(
-            Ranges::default(),
-            |mut ranges, (row_index, column_index)| {
-                ranges.min_row = ranges.min_row.min(*row_index);
-                ranges.max_row = ranges.max_row.max(*row_index);
-                ranges.min_column = ranges.min_column.min(*column_index);
-                ranges.max_column = ranges.max_column.max(*column_index);
-                ranges
-            },
-        );
+    fn new(
+        height: usize,
+        width: usize,
+        top: isize,
+        left: isize,
+        is_light_background: bool,
+    ) -> Self {
+        Self {
+            height,
+            width,
+            top,
+            left,
+            light_pixels: BitVec::new(height * width),
+            is_light_background,
+        }
     }
 
     fn is_light(&self, row: isize, column: isize) -> bool {
-        let ranges = &self.ranges;
-
-        if (ranges.min_row..=ranges.max_row).contains(&row)
-            && (ranges.min_column..=ranges.max_column).contains(&column)
-        {
-            self.light_positions.contains(&(row, column))
+        let (row, column) = (row - self.top, column - self.left);
+        if (0..self.height as isize).contains(&row) && (0..self.width as isize).contains(&column) {
+            self.light_pixels
+                .get(row as usize * self.width + column as usize)
         } else {
-            self.is_light_outside_ranges
+            self.is_light_background
         }
     }
 
-    fn get_square(&self, row: isize, column: isize) -> Square {
-        Square::from([
-            [
-                self.is_light(row - 1, column - 1),
-                self.is_light(row - 1, column),
-                self.is_light(row - 1, column + 1),
-            ],
-            [
-                self.is_light(row, column - 1),
-                self.is_light(row, column),
-                self.is_light(row, column + 1),
-            ],
-            [
-                self.is_light(row + 1, column - 1),
-                self.is_light(row + 1, column),
-                self.is_light(row + 1, column + 1),
-            ],
-        ])
+    /// Index in the algorithm of the square centered in the given pixel (top-left pixel is the most significant bit)
+    fn square_value(&self, row: isize, column: isize, kernel_size: usize) -> usize {
+        let radius = (kernel_size / 2) as isize;
+        let mut value = 0;
+        for square_row in (row - radius)..=(row + radius) {
+            for square_column in (column - radius)..=(column + radius) {
+                value = (value << 1) | usize::from(self.is_light(square_row, square_column));
+            }
+        }
+        value
     }
 
-    fn apply_image_enhancement_algorithm(
-        &mut self,
-        algorithm: &ImageEnhancementAlgorithm,
-        count: usize,
-    ) {
-        for _ in 0..count {
-            let ranges = &self.ranges;
-            let light_positions = ((ranges.min_row - 1)..=(ranges.max_row + 1))
-                .flat_map(|row_index| {
-                    ((ranges.min_column - 1)..=(ranges.max_column + 1))
-                        .filter_map(|column_index| {
-                            if algorithm
-                                .is_light(self.get_square(row_index, column_index).to_value())
-                            {
-                                Some((row_index, column_index))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<HashSet<_>>()
-                })
-                .collect();
-            self.is_light_outside_ranges = if self.is_light_outside_ranges {
-                algorithm.is_light(ALGORITHM_LENGTH - 1)
-            } else {
-                algorithm.is_light(0)
-            };
-            self.light_positions = light_positions;
-            self.update_row_column_ranges();
+    /// Applies the algorithm once. The bounding box grows by the kernel radius on each side.
+    fn enhance(&self, algorithm: &ImageEnhancementAlgorithm) -> Self {
+        let radius = algorithm.kernel_size / 2;
+        let mut enhanced = Self::new(
+            self.height + 2 * radius,
+            self.width + 2 * radius,
+            self.top - radius as isize,
+            self.left - radius as isize,
+            algorithm.next_background(self.is_light_background),
+        );
+        for row in 0..enhanced.height {
+            for column in 0..enhanced.width {
+                let value = self.square_value(
+                    enhanced.top + row as isize,
+                    enhanced.left + column as isize,
+                    algorithm.kernel_size,
+                );
+                if algorithm.is_light(value) {
+                    enhanced.light_pixels.set(row * enhanced.width + column);
+                }
+            }
         }
+        enhanced
     }
 
-    fn light_pixels_count(&self) -> usize {
-        self.light_positions.len()
+    fn light_pixels_count(&self) -> anyhow::Result<usize> {
+        anyhow::ensure!(
+            !self.is_light_background,
+            "The background is lit, so infinite pixels are lit"
+        );
+        Ok(self.light_pixels.count_ones())
     }
-}
 
-fn part01(input: &Input) -> usize {
-    let mut processed_image = input.base_image.clone();
-    processed_image.apply_image_enhancement_algorithm(&input.image_enhancement_algorithm, 2);
-    processed_image.light_pixels_count()
-}
-
-fn part02(input: &Input) -> usize {
-    let mut processed_image = input.base_image.clone();
-    processed_image.apply_image_enhancement_algorithm(&input.image_enhancement_algorithm, 50);
-    processed_image.light_pixels_count()
+    /// Exports the bounding box of the image in the plain PBM format (lit pixels are black)
+    fn write_pbm(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "P1")?;
+        writeln!(
+            writer,
+            "# top-left pixel: ({}, {}), lit background: {}",
+            self.top, self.left, self.is_light_background
+        )?;
+        writeln!(writer, "{} {}", self.width, self.height)?;
+        for row in 0..self.height {
+            let line = (0..self.width)
+                .map(|column| {
+                    if self.light_pixels.get(row * self.width + column) {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
 }
 
 impl std::fmt::Display for Image {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ranges = &self.ranges;
-        for row_index in ranges.min_row..=ranges.max_row {
-            for column_index in ranges.min_column..=ranges.max_column {
+        for row in 0..self.height {
+            for column in 0..self.width {
                 write!(
                     f,
                     "{}",
-                    if self.is_light(row_index, column_index) {
+                    if self.light_pixels.get(row * self.width + column) {
                         '#'
                     } else {
                         '.'
@@ -281,11 +284,42 @@ impl std::fmt::Display for Image {
     }
 }
 
+/// Applies the algorithm `count` times, invoking `on_step` with the image obtained after each step
+fn enhance(
+    input: &Input,
+    count: usize,
+    mut on_step: impl FnMut(usize, &Image) -> anyhow::Result<()>,
+) -> anyhow::Result<Image> {
+    let mut image = input.base_image.clone();
+    for step in 1..=count {
+        image = image.enhance(&input.image_enhancement_algorithm);
+        on_step(step, &image)?;
+    }
+    Ok(image)
+}
+
+fn part01(input: &Input) -> anyhow::Result<usize> {
+    enhance(input, 2, |_, _| Ok(()))?.light_pixels_count()
+}
+
+fn part02(input: &Input, pbm_dir: Option<&Path>) -> anyhow::Result<usize> {
+    if let Some(pbm_dir) = pbm_dir {
+        std::fs::create_dir_all(pbm_dir)?;
+        input.base_image.write_pbm(&pbm_dir.join("frame_00.pbm"))?;
+    }
+    enhance(input, 50, |step, image| match pbm_dir {
+        Some(pbm_dir) => image.write_pbm(&pbm_dir.join(format!("frame_{:02}.pbm", step))),
+        None => Ok(()),
+    })?
+    .light_pixels_count()
+}
+
 fn main() -> anyhow::Result<()> {
-    let input = Input::try_from(input_lines(INPUT)?)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input = Input::try_from(lines)?;
 
-    println!("Part 1: 5249 {}", part01(&input));
-    println!("Part 2: 15714 {}", part02(&input));
+    println!("Part 1: {}", part01(&input)?);
+    println!("Part 2: {}", part02(&input, opts.pbm_dir.as_deref())?);
 
     Ok(())
 }
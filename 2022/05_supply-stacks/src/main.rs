@@ -1,4 +1,5 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
@@ -6,6 +7,17 @@ use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the supply stacks after each move
+    #[arg(long)]
+    dump_frames: bool,
+
+    /// Additionally rearranges the stacks with a crane able to lift at most the given number of crates at once
+    #[arg(long)]
+    crane_capacity: Option<usize>,
+}
+
 #[derive(Clone, Debug)]
 struct Move {
     quantity: usize,
@@ -20,6 +32,15 @@ impl FromStr for Move {
         Ok(Self { quantity, src, dst })
     }
 }
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.quantity, self.src, self.dst
+        )
+    }
+}
 
 #[derive(Clone, Debug)]
 struct SupplyStacks {
@@ -52,31 +73,110 @@ impl TryFrom<&[String]> for SupplyStacks {
     }
 }
 
+impl std::fmt::Display for SupplyStacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(VecDeque::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let line = self
+                .stacks
+                .iter()
+                .map(|stack| {
+                    stack.get(level).map_or_else(
+                        || "   ".to_string(),
+                        |stack_crate| format!("[{stack_crate}]"),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        let footer = (1..=self.stacks.len())
+            .map(|stack_id| format!("{stack_id:^3}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{}", footer.trim_end())
+    }
+}
+
 impl SupplyStacks {
-    fn apply_crate_mover_9000(&mut self, move_: &Move) -> anyhow::Result<()> {
-        for _ in 0..move_.quantity {
-            let stack_crate = self.stacks[move_.src - 1]
-                .pop_back()
-                .ok_or_else(|| anyhow::anyhow!("Expected to have found a crate"))?;
-            self.stacks[move_.dst - 1].push_back(stack_crate);
+    fn stack_mut(&mut self, stack_id: usize) -> anyhow::Result<&mut VecDeque<char>> {
+        stack_id
+            .checked_sub(1)
+            .and_then(|idx| self.stacks.get_mut(idx))
+            .ok_or_else(|| anyhow::anyhow!("Stack {} does not exist", stack_id))
+    }
+
+    /// Removes the top `quantity` crates from the stack.
+    /// The crates are returned in the order they had in the stack (bottom to top).
+    fn lift(&mut self, stack_id: usize, quantity: usize) -> anyhow::Result<Vec<char>> {
+        let stack = self.stack_mut(stack_id)?;
+        if stack.len() < quantity {
+            return Err(anyhow::anyhow!(
+                "Expected to have found {} crates on stack {}, found {}",
+                quantity,
+                stack_id,
+                stack.len()
+            ));
         }
+        Ok(stack.split_off(stack.len() - quantity).into())
+    }
+
+    /// Places the crates on top of the stack (first crate is placed first).
+    fn place(
+        &mut self,
+        stack_id: usize,
+        crates: impl IntoIterator<Item = char>,
+    ) -> anyhow::Result<()> {
+        self.stack_mut(stack_id)?.extend(crates);
         Ok(())
     }
 
-    fn apply_crate_mover_9001(&mut self, move_: &Move) -> anyhow::Result<()> {
-        let mut tmp_stack = VecDeque::with_capacity(move_.quantity);
+    fn top_crates(&self) -> String {
+        self.stacks.iter().filter_map(VecDeque::back).collect()
+    }
+}
+
+/// Model of the crane used to rearrange the crates.
+/// Different models differ in the way crates are moved between stacks.
+trait CraneModel {
+    fn apply(&self, supply_stacks: &mut SupplyStacks, move_: &Move) -> anyhow::Result<()>;
+}
+
+/// Moves one crate at a time
+struct CrateMover9000;
+impl CraneModel for CrateMover9000 {
+    fn apply(&self, supply_stacks: &mut SupplyStacks, move_: &Move) -> anyhow::Result<()> {
+        let crates = supply_stacks.lift(move_.src, move_.quantity)?;
+        supply_stacks.place(move_.dst, crates.into_iter().rev())
+    }
+}
+
+/// Moves all the crates at once
+struct CrateMover9001;
+impl CraneModel for CrateMover9001 {
+    fn apply(&self, supply_stacks: &mut SupplyStacks, move_: &Move) -> anyhow::Result<()> {
+        let crates = supply_stacks.lift(move_.src, move_.quantity)?;
+        supply_stacks.place(move_.dst, crates)
+    }
+}
 
-        for _ in 0..move_.quantity {
-            tmp_stack.push_back(
-                self.stacks[move_.src - 1]
-                    .pop_back()
-                    .ok_or_else(|| anyhow::anyhow!("Expected to have found a crate"))?,
-            );
+/// Moves at most `capacity` crates at once.
+/// Crates within a chunk keep their order, while the chunks end up reversed.
+struct CrateMoverWithCapacity {
+    capacity: usize,
+}
+impl CraneModel for CrateMoverWithCapacity {
+    fn apply(&self, supply_stacks: &mut SupplyStacks, move_: &Move) -> anyhow::Result<()> {
+        if self.capacity == 0 {
+            return Err(anyhow::anyhow!("Crane capacity must be greater than 0"));
         }
-        while let Some(stack_crate) = tmp_stack.pop_back() {
-            self.stacks[move_.dst - 1].push_back(stack_crate);
+        let mut remaining = move_.quantity;
+        while remaining > 0 {
+            let quantity = remaining.min(self.capacity);
+            let crates = supply_stacks.lift(move_.src, quantity)?;
+            supply_stacks.place(move_.dst, crates)?;
+            remaining -= quantity;
         }
-
         Ok(())
     }
 }
@@ -105,35 +205,58 @@ impl TryFrom<&[String]> for Input {
     }
 }
 
-fn part01(mut input: Input) -> anyhow::Result<String> {
-    for move_ in input.moves {
-        input.supply_stacks.apply_crate_mover_9000(&move_)?;
+impl Input {
+    /// Applies all the moves with the given crane, invoking `on_move` with the
+    /// supply stacks obtained after each move.
+    fn rearrange(
+        mut self,
+        crane: &impl CraneModel,
+        mut on_move: impl FnMut(&Move, &SupplyStacks),
+    ) -> anyhow::Result<SupplyStacks> {
+        for move_ in &self.moves {
+            crane.apply(&mut self.supply_stacks, move_)?;
+            on_move(move_, &self.supply_stacks);
+        }
+        Ok(self.supply_stacks)
     }
-    Ok(input
-        .supply_stacks
-        .stacks
-        .iter()
-        .filter_map(VecDeque::back)
-        .collect())
 }
 
-fn part02(mut input: Input) -> anyhow::Result<String> {
-    for move_ in input.moves {
-        input.supply_stacks.apply_crate_mover_9001(&move_)?;
+fn run(input: Input, crane: &impl CraneModel, dump_frames: bool) -> anyhow::Result<String> {
+    if dump_frames {
+        println!("{}\n", input.supply_stacks);
     }
-    Ok(input
-        .supply_stacks
-        .stacks
-        .iter()
-        .filter_map(VecDeque::back)
-        .collect())
+    let supply_stacks = input.rearrange(crane, |move_, supply_stacks| {
+        if dump_frames {
+            println!("{move_}\n{supply_stacks}\n");
+        }
+    })?;
+    Ok(supply_stacks.top_crates())
+}
+
+fn part01(input: Input, dump_frames: bool) -> anyhow::Result<String> {
+    run(input, &CrateMover9000, dump_frames)
+}
+
+fn part02(input: Input, dump_frames: bool) -> anyhow::Result<String> {
+    run(input, &CrateMover9001, dump_frames)
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
     let input = Input::try_from(&lines[..])?;
 
-    println!("Part 1: {}", part01(input.clone())?);
-    println!("Part 2: {}", part02(input)?);
+    println!("Part 1: {}", part01(input.clone(), opts.dump_frames)?);
+    if let Some(capacity) = opts.crane_capacity {
+        println!(
+            "Crane with capacity {}: {}",
+            capacity,
+            run(
+                input.clone(),
+                &CrateMoverWithCapacity { capacity },
+                opts.dump_frames
+            )?
+        );
+    }
+    println!("Part 2: {}", part02(input, opts.dump_frames)?);
     Ok(())
 }
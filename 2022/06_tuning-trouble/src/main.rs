@@ -1,35 +1,106 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::io::{BufReader, Read};
 
 const INPUT: &str = include_str!("../input.txt");
 
-fn find_start(input: &str, sequence_length: usize) -> usize {
-    input
-        .as_bytes()
-        .windows(sequence_length)
-        .enumerate()
-        .find(|(_, chars)| {
-            (0..(sequence_length - 1))
-                .flat_map(|index1| {
-                    (index1 + 1..sequence_length).map(move |index2| (index1, index2))
-                })
-                .all(|(index1, index2)| chars[index1] != chars[index2])
-        })
-        .map_or(0, |(idx, _)| idx)
-        + sequence_length
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Window sizes reported by `--all-markers` (comma separated)
+    #[arg(long, value_delimiter = ',', default_values_t = [4, 14])]
+    window_sizes: Vec<usize>,
+
+    /// Prints the positions of all the markers for each window size
+    #[arg(long)]
+    all_markers: bool,
+}
+
+/// Tracks the longest run of distinct letters ending at the last received letter.
+///
+/// The last position of each letter is kept in a table, so every letter is processed in constant time.
+#[derive(Debug, Default)]
+struct DistinctRun {
+    last_seen: [Option<usize>; 26],
+    run_start: usize,
+    processed: usize,
+}
+
+impl DistinctRun {
+    /// Registers the next letter of the datastream returning the length of the run
+    fn push(&mut self, letter: u8) -> anyhow::Result<usize> {
+        anyhow::ensure!(
+            letter.is_ascii_lowercase(),
+            "Unexpected character `{letter}` at position {position}",
+            letter = letter as char,
+            position = self.processed
+        );
+        let slot = &mut self.last_seen[usize::from(letter - b'a')];
+        if let Some(last_seen) = *slot {
+            self.run_start = self.run_start.max(last_seen + 1);
+        }
+        *slot = Some(self.processed);
+        self.processed += 1;
+        Ok(self.processed - self.run_start)
+    }
+}
+
+/// Letters of the datastream, until its end or the end of the line
+fn datastream<R: Read>(source: R) -> impl Iterator<Item = std::io::Result<u8>> {
+    BufReader::new(source)
+        .bytes()
+        .take_while(|byte| !matches!(byte, Ok(b'\n' | b'\r')))
+}
+
+/// Number of characters processed before the end of the first marker
+/// (sequence of `window_size` distinct letters), if any
+fn find_start<R: Read>(source: R, window_size: usize) -> anyhow::Result<Option<usize>> {
+    let mut run = DistinctRun::default();
+    for letter in datastream(source) {
+        if run.push(letter?)? >= window_size {
+            return Ok(Some(run.processed));
+        }
+    }
+    Ok(None)
 }
 
-fn part01(input: &str) -> usize {
-    find_start(input, 4)
+/// Ends of all the markers for each window size, in a single pass over the datastream
+fn find_all_markers<R: Read>(source: R, window_sizes: &[usize]) -> anyhow::Result<Vec<Vec<usize>>> {
+    let mut run = DistinctRun::default();
+    let mut markers = vec![vec![]; window_sizes.len()];
+    for letter in datastream(source) {
+        let run_length = run.push(letter?)?;
+        for (window_size, positions) in window_sizes.iter().zip(&mut markers) {
+            if run_length >= *window_size {
+                positions.push(run.processed);
+            }
+        }
+    }
+    Ok(markers)
 }
 
-fn part02(input: &str) -> usize {
-    find_start(input, 14)
+fn part01(input: &str) -> anyhow::Result<usize> {
+    find_start(input.as_bytes(), 4)?
+        .ok_or_else(|| anyhow::anyhow!("No start-of-packet marker found"))
+}
+
+fn part02(input: &str) -> anyhow::Result<usize> {
+    find_start(input.as_bytes(), 14)?
+        .ok_or_else(|| anyhow::anyhow!("No start-of-message marker found"))
 }
 
 fn main() -> anyhow::Result<()> {
-    let input = &input_lines(INPUT)?[0];
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input = &lines[0];
+
+    if opts.all_markers {
+        let markers = find_all_markers(input.as_bytes(), &opts.window_sizes)?;
+        for (window_size, positions) in opts.window_sizes.iter().zip(markers) {
+            let positions: Vec<String> = positions.iter().map(ToString::to_string).collect();
+            println!("Window {}: {}", window_size, positions.join(","));
+        }
+    }
 
-    println!("Part 1: {}", part01(input));
-    println!("Part 2: {}", part02(input));
+    println!("Part 1: {}", part01(input)?);
+    println!("Part 2: {}", part02(input)?);
     Ok(())
 }
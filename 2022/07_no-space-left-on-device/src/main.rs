@@ -1,4 +1,5 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
@@ -9,20 +10,43 @@ use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the reconstructed directory tree
+    #[arg(long)]
+    tree: bool,
+
+    /// Total size of the disk
+    #[arg(long, default_value_t = 70_000_000)]
+    disk_size: usize,
+
+    /// Free space required to run the update
+    #[arg(long, default_value_t = 30_000_000)]
+    required_free_space: usize,
+}
+
 #[derive(Debug)]
 enum LsOutput {
-    Dir,
-    File { size: usize },
+    Dir { name: String },
+    File { name: String, size: usize },
 }
 impl FromStr for LsOutput {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Ok(_name) = scan_fmt!(s, "dir {}", String) {
-            Ok(Self::Dir)
+        if let Ok(name) = scan_fmt!(s, "dir {}", String) {
+            Ok(Self::Dir { name })
         } else {
-            let (size, _name) = scan_fmt!(s, "{} {}", usize, String)?;
-            Ok(Self::File { size })
+            let (size, name) = scan_fmt!(s, "{} {}", usize, String)?;
+            Ok(Self::File { name, size })
+        }
+    }
+}
+
+impl LsOutput {
+    fn name(&self) -> &str {
+        match self {
+            Self::Dir { name } | Self::File { name, .. } => name,
         }
     }
 }
@@ -45,7 +69,11 @@ impl Cmd {
             match cmd_line {
                 v if v.starts_with("$ cd ") => {
                     let new_path = v.replace("$ cd ", "");
-                    if let Some(cwd) = cwd {
+                    if new_path == "/" {
+                        Ok(Self::CD {
+                            cwd: Path::new("/").to_path_buf(),
+                        })
+                    } else if let Some(cwd) = cwd {
                         if new_path == ".." {
                             Ok(Self::CD {
                                 cwd: cwd
@@ -69,7 +97,7 @@ impl Cmd {
                         })
                     }
                 }
-                v if v == "$ ls" => {
+                "$ ls" => {
                     let mut output: Vec<LsOutput> = vec![];
                     while matches!(lines.peek(), Some(line) if !Self::is_cmd(line)) {
                         output.push(
@@ -131,57 +159,226 @@ impl TryFrom<&[String]> for Input {
         Ok(Self { cmds })
     }
 }
-impl Input {
-    fn directory_to_size(&self) -> BTreeMap<&Path, usize> {
-        let mut directory_to_size: BTreeMap<&Path, usize> =
-            self.cmds.iter().map(|cmd| (cmd.cwd(), 0)).collect();
-
-        for cmd in &self.cmds {
-            if let Cmd::LS { cwd, output } = cmd {
-                let direct_file_size: usize = output
-                    .iter()
-                    .map(|ls_output| match ls_output {
-                        LsOutput::File { size, .. } => *size,
-                        LsOutput::Dir { .. } => 0,
-                    })
-                    .sum();
+#[derive(Debug)]
+enum Entry {
+    Directory(usize),
+    File(usize),
+}
+
+#[derive(Debug, Default)]
+struct Directory {
+    name: String,
+    parent: Option<usize>,
+    entries: BTreeMap<String, Entry>,
+    listed: bool,
+}
+
+/// In-memory directory tree reconstructed from the shell transcript.
+/// Directories are stored in creation order, so a directory always follows its parent.
+#[derive(Debug)]
+struct FileSystem {
+    directories: Vec<Directory>,
+}
+
+impl FileSystem {
+    const ROOT: usize = 0;
+
+    fn path(&self, directory_id: usize) -> PathBuf {
+        let mut names = vec![];
+        let mut current = Some(directory_id);
+        while let Some(id) = current {
+            names.push(self.directories[id].name.as_str());
+            current = self.directories[id].parent;
+        }
+        names.iter().rev().collect()
+    }
+
+    fn subdirectory(&mut self, directory_id: usize, name: &str) -> anyhow::Result<usize> {
+        match self.directories[directory_id].entries.get(name) {
+            Some(Entry::Directory(id)) => Ok(*id),
+            Some(Entry::File(_)) => Err(anyhow::anyhow!(
+                "{} is a file, expected a directory",
+                self.path(directory_id).join(name).display()
+            )),
+            None => {
+                let id = self.directories.len();
+                self.directories.push(Directory {
+                    name: name.to_string(),
+                    parent: Some(directory_id),
+                    ..Directory::default()
+                });
+                self.directories[directory_id]
+                    .entries
+                    .insert(name.to_string(), Entry::Directory(id));
+                Ok(id)
+            }
+        }
+    }
 
-                for (dir, size) in &mut directory_to_size {
-                    if cwd.starts_with(dir) {
-                        *size += direct_file_size;
+    fn directory(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let mut directory_id = Self::ROOT;
+        for component in path.iter().skip(1) {
+            let name = component
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("{} is not a valid path", path.display()))?;
+            directory_id = self.subdirectory(directory_id, name)?;
+        }
+        Ok(directory_id)
+    }
+
+    fn register_listing(&mut self, directory_id: usize, output: &[LsOutput]) -> anyhow::Result<()> {
+        let path = self.path(directory_id);
+        let conflict = || anyhow::anyhow!("Conflicting 'ls' output for {}", path.display());
+
+        let directory = &self.directories[directory_id];
+        let mut names: Vec<&str> = output.iter().map(LsOutput::name).collect();
+        names.sort_unstable();
+        names.dedup();
+        if names.len() != output.len()
+            || (directory.listed && names.len() != directory.entries.len())
+            || directory
+                .entries
+                .keys()
+                .any(|name| names.binary_search(&name.as_str()).is_err())
+        {
+            return Err(conflict());
+        }
+
+        for ls_output in output {
+            match ls_output {
+                LsOutput::Dir { name } => {
+                    self.subdirectory(directory_id, name)?;
+                }
+                LsOutput::File { name, size } => {
+                    match self.directories[directory_id].entries.get(name) {
+                        Some(Entry::File(existing_size)) if existing_size == size => {}
+                        Some(_) => return Err(conflict()),
+                        None => {
+                            self.directories[directory_id]
+                                .entries
+                                .insert(name.to_string(), Entry::File(*size));
+                        }
                     }
                 }
             }
         }
-        directory_to_size
+        self.directories[directory_id].listed = true;
+        Ok(())
+    }
+
+    /// Cumulative size of each directory, indexed by directory id
+    fn directory_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self
+            .directories
+            .iter()
+            .map(|directory| {
+                directory
+                    .entries
+                    .values()
+                    .map(|entry| match entry {
+                        Entry::File(size) => *size,
+                        Entry::Directory(_) => 0,
+                    })
+                    .sum()
+            })
+            .collect();
+        for (id, directory) in self.directories.iter().enumerate().rev() {
+            if let Some(parent) = directory.parent {
+                sizes[parent] += sizes[id];
+            }
+        }
+        sizes
+    }
+
+    fn fmt_directory(
+        &self,
+        f: &mut std::fmt::Formatter,
+        sizes: &[usize],
+        directory_id: usize,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let directory = &self.directories[directory_id];
+        writeln!(
+            f,
+            "{:indent$}- {} (dir, size={})",
+            "",
+            directory.name,
+            sizes[directory_id],
+            indent = 2 * depth
+        )?;
+        for (name, entry) in &directory.entries {
+            match entry {
+                Entry::Directory(id) => self.fmt_directory(f, sizes, *id, depth + 1)?,
+                Entry::File(size) => writeln!(
+                    f,
+                    "{:indent$}- {} (file, size={})",
+                    "",
+                    name,
+                    size,
+                    indent = 2 * (depth + 1)
+                )?,
+            }
+        }
+        Ok(())
     }
 }
 
-fn part01(input: &Input) -> usize {
-    input
-        .directory_to_size()
-        .values()
-        .filter(|size| size < &&100_000)
+impl std::fmt::Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.fmt_directory(f, &self.directory_sizes(), Self::ROOT, 0)
+    }
+}
+
+impl TryFrom<&Input> for FileSystem {
+    type Error = anyhow::Error;
+    fn try_from(input: &Input) -> Result<Self, Self::Error> {
+        let mut file_system = Self {
+            directories: vec![Directory {
+                name: "/".to_string(),
+                ..Directory::default()
+            }],
+        };
+        for cmd in &input.cmds {
+            let directory_id = file_system.directory(cmd.cwd())?;
+            if let Cmd::LS { output, .. } = cmd {
+                file_system.register_listing(directory_id, output)?;
+            }
+        }
+        Ok(file_system)
+    }
+}
+
+fn part01(file_system: &FileSystem) -> usize {
+    file_system
+        .directory_sizes()
+        .into_iter()
+        .filter(|size| size < &100_000)
         .sum()
 }
 
-fn part02(input: &Input) -> usize {
-    let directory_to_size = input.directory_to_size();
-    let space_needed_for_update = 30_000_000;
-    let disk_size = 70_000_000;
-    let space_to_release = directory_to_size[&Path::new("/")] + space_needed_for_update - disk_size;
-    directory_to_size
-        .values()
-        .filter(|size| size >= &&space_to_release)
+fn part02(file_system: &FileSystem, disk_size: usize, required_free_space: usize) -> usize {
+    let directory_sizes = file_system.directory_sizes();
+    let space_to_release =
+        (directory_sizes[FileSystem::ROOT] + required_free_space).saturating_sub(disk_size);
+    directory_sizes
+        .into_iter()
+        .filter(|size| size >= &space_to_release)
         .min()
-        .copied()
         .unwrap_or(0)
 }
 
 fn main() -> anyhow::Result<()> {
-    let input = Input::try_from(&input_lines(INPUT)?[..])?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input = Input::try_from(&lines[..])?;
+    let file_system = FileSystem::try_from(&input)?;
 
-    println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    if opts.tree {
+        print!("{}", file_system);
+    }
+    println!("Part 1: {}", part01(&file_system));
+    println!(
+        "Part 2: {}",
+        part02(&file_system, opts.disk_size, opts.required_free_space)
+    );
     Ok(())
 }
@@ -1,123 +1,267 @@
-use helpers::input_lines;
+use clap::{Args, ValueEnum};
+use helpers::input_lines_with_opts;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 
 const INPUT: &str = include_str!("../input.txt");
 
-fn part01(grid: &[Vec<u8>]) -> usize {
-    let n_rows = grid.len();
-    let n_cols = grid[0].len();
-    (0..n_rows)
-        .map(|row_id: usize| {
-            if row_id == 0 || row_id == n_rows - 1 {
-                n_cols
-            } else {
-                (0..n_cols)
-                    .filter(|col_id: &usize| {
-                        let col_id: usize = *col_id;
-                        (
-                            // border
-                            col_id == 0 || col_id == n_cols - 1
-                        ) || (
-                            // visible from left
-                            (0..col_id).rev().all(|other_col_id| {
-                                grid[row_id][col_id] > grid[row_id][other_col_id]
-                            })
-                        ) || (
-                            // visible from right
-                            (col_id + 1..n_cols).all(|other_col_id| {
-                                grid[row_id][col_id] > grid[row_id][other_col_id]
-                            })
-                        ) || (
-                            // visible from top
-                            (0..row_id).rev().all(|other_row_id| {
-                                grid[row_id][col_id] > grid[other_row_id][col_id]
-                            })
-                        ) || (
-                            // visible from bottom
-                            (row_id + 1..n_rows).all(|other_row_id| {
-                                grid[row_id][col_id] > grid[other_row_id][col_id]
-                            })
-                        )
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the heatmap of the given metric
+    #[arg(long, value_enum)]
+    heatmap: Option<Heatmap>,
+
+    /// Dumps the visibility and viewing distances of every tree as CSV
+    #[arg(long)]
+    csv: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Heatmap {
+    /// Number of edges from which each tree is visible
+    Visibility,
+    /// Scenic score of each tree, on a 10 levels scale
+    ScenicScore,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    const ALL: [Self; 4] = [Self::Left, Self::Right, Self::Up, Self::Down];
+
+    fn symbol(self) -> char {
+        match self {
+            Self::Left => 'L',
+            Self::Right => 'R',
+            Self::Up => 'U',
+            Self::Down => 'D',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct TreeView {
+    /// Whether the tree is visible from the edge in each direction
+    visible_from: [bool; 4],
+    /// Number of trees seen looking in each direction
+    viewing_distances: [usize; 4],
+}
+
+impl TreeView {
+    fn is_visible(&self) -> bool {
+        self.visible_from.iter().any(|visible| *visible)
+    }
+
+    fn scenic_score(&self) -> usize {
+        self.viewing_distances.iter().product()
+    }
+}
+
+#[derive(Debug)]
+struct Forest {
+    n_rows: usize,
+    n_cols: usize,
+    heights: Vec<Vec<u8>>,
+}
+
+impl TryFrom<&[String]> for Forest {
+    type Error = anyhow::Error;
+
+    fn try_from(lines: &[String]) -> Result<Self, Self::Error> {
+        anyhow::ensure!(!lines.is_empty(), "Expected to have at least one row");
+        let heights: Vec<Vec<u8>> = lines
+            .iter()
+            .map(|line| {
+                line.bytes()
+                    .map(|b| {
+                        if b.is_ascii_digit() {
+                            Ok(b - b'0')
+                        } else {
+                            Err(anyhow::anyhow!("Invalid tree height: {}", b as char))
+                        }
                     })
-                    .count()
-            }
+                    .collect::<Result<_, _>>()
+            })
+            .collect::<Result<_, _>>()?;
+        anyhow::ensure!(
+            heights.iter().all(|row| row.len() == heights[0].len()),
+            "Expected to have at all the rows with the same number of columns"
+        );
+
+        Ok(Self {
+            n_rows: heights.len(),
+            n_cols: heights[0].len(),
+            heights,
         })
-        .sum()
+    }
 }
 
-fn part02(grid: &[Vec<u8>]) -> usize {
-    let n_rows = grid.len();
-    let n_cols = grid[0].len();
-
-    let viewing_score = |row_id: usize, col_id: usize| {
-        if row_id == 0 || col_id == 0 || row_id == n_rows - 1 || col_id == n_cols - 1 {
-            0
-        } else {
-            let mut visibility_left = (0..col_id)
-                .rev()
-                .take_while(|other_col_id| grid[row_id][col_id] > grid[row_id][*other_col_id])
-                .count();
-            if visibility_left < col_id {
-                // We did not reach the border
-                if grid[row_id][col_id - visibility_left - 1] >= grid[row_id][col_id] {
-                    visibility_left += 1;
-                }
+/// For every tree of the line, computes how many trees it sees looking towards the start of the line
+/// and whether it is visible from the start of the line.
+///
+/// A monotonic stack keeps the trees that can still block the view of the next ones,
+/// so each tree is pushed and popped at most once.
+fn look_back(heights: &[u8]) -> Vec<(usize, bool)> {
+    let mut blocking: Vec<usize> = vec![];
+    heights
+        .iter()
+        .enumerate()
+        .map(|(index, height)| {
+            while blocking
+                .last()
+                .is_some_and(|other_index| heights[*other_index] < *height)
+            {
+                blocking.pop();
             }
+            let result = match blocking.last() {
+                Some(other_index) => (index - other_index, false),
+                None => (index, true),
+            };
+            blocking.push(index);
+            result
+        })
+        .collect()
+}
 
-            let mut visibility_right = (col_id + 1..n_cols)
-                .take_while(|other_col_id| grid[row_id][col_id] > grid[row_id][*other_col_id])
-                .count();
-            if col_id + visibility_right < n_cols - 1 {
-                // We did not reach the border
-                if grid[row_id][col_id + visibility_right + 1] >= grid[row_id][col_id] {
-                    visibility_right += 1;
+impl Forest {
+    /// Visibility and viewing distances of every tree, in `O(n_rows * n_cols)`
+    fn tree_views(&self) -> Vec<Vec<TreeView>> {
+        let mut views = vec![vec![TreeView::default(); self.n_cols]; self.n_rows];
+        let mut register =
+            |direction: Direction, positions: &[(usize, usize)], results: Vec<(usize, bool)>| {
+                for ((row, col), (viewing_distance, visible)) in positions.iter().zip(results) {
+                    views[*row][*col].viewing_distances[direction as usize] = viewing_distance;
+                    views[*row][*col].visible_from[direction as usize] = visible;
                 }
-            }
+            };
 
-            let mut visibility_up = (0..row_id)
-                .rev()
-                .take_while(|other_row_id| grid[row_id][col_id] > grid[*other_row_id][col_id])
-                .count();
-            if visibility_up < row_id {
-                // We did not reach the border
-                if grid[row_id - visibility_up - 1][col_id] >= grid[row_id][col_id] {
-                    visibility_up += 1;
-                }
-            }
+        let lines = (0..self.n_rows)
+            .map(|row| {
+                let positions: Vec<_> = (0..self.n_cols).map(|col| (row, col)).collect();
+                (Direction::Left, Direction::Right, positions)
+            })
+            .chain((0..self.n_cols).map(|col| {
+                let positions: Vec<_> = (0..self.n_rows).map(|row| (row, col)).collect();
+                (Direction::Up, Direction::Down, positions)
+            }));
+        for (backward, forward, mut positions) in lines {
+            let heights: Vec<u8> = positions
+                .iter()
+                .map(|(row, col)| self.heights[*row][*col])
+                .collect();
+            register(backward, &positions, look_back(&heights));
 
-            let mut visibility_down = (row_id + 1..n_rows)
-                .take_while(|other_row_id| grid[row_id][col_id] > grid[*other_row_id][col_id])
-                .count();
-            if row_id + visibility_down < n_rows - 1 {
-                // We did not reach the border
-                if grid[row_id + visibility_down + 1][col_id] >= grid[row_id][col_id] {
-                    visibility_down += 1;
-                }
-            }
+            positions.reverse();
+            let heights: Vec<u8> = heights.into_iter().rev().collect();
+            register(forward, &positions, look_back(&heights));
+        }
+
+        views
+    }
+}
+
+fn render_heatmap(views: &[Vec<TreeView>], heatmap: Heatmap) -> String {
+    const LEVELS: &[u8] = b" .:-=+*#%@";
+    let max_scenic_score = views
+        .iter()
+        .flatten()
+        .map(TreeView::scenic_score)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    views
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|view| match heatmap {
+                    Heatmap::Visibility => {
+                        let count = view.visible_from.iter().filter(|visible| **visible).count();
+                        if count == 0 {
+                            '.'
+                        } else {
+                            char::from(b'0' + count as u8)
+                        }
+                    }
+                    Heatmap::ScenicScore => {
+                        let level = view.scenic_score() * (LEVELS.len() - 1) / max_scenic_score;
+                        char::from(LEVELS[level])
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-            visibility_left * visibility_right * visibility_up * visibility_down
+fn write_csv(forest: &Forest, views: &[Vec<TreeView>], path: &PathBuf) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(
+        writer,
+        "row,column,height,visible_from,left,right,up,down,scenic_score"
+    )?;
+    for (row, row_views) in views.iter().enumerate() {
+        for (col, view) in row_views.iter().enumerate() {
+            let visible_from: String = Direction::ALL
+                .iter()
+                .filter(|direction| view.visible_from[**direction as usize])
+                .map(|direction| direction.symbol())
+                .collect();
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                row,
+                col,
+                forest.heights[row][col],
+                visible_from,
+                view.viewing_distances[Direction::Left as usize],
+                view.viewing_distances[Direction::Right as usize],
+                view.viewing_distances[Direction::Up as usize],
+                view.viewing_distances[Direction::Down as usize],
+                view.scenic_score()
+            )?;
         }
-    };
-
-    (1..(n_rows - 1))
-        .flat_map(|row_id| (1..(n_cols - 1)).map(move |col_id| (row_id, col_id)))
-        .map(
-            // Generated row_id and col_id excluding the borders as the score will be
-            // 0 by definition ("at least one of its viewing distances will be zero")
-            |(row_id, col_id)| viewing_score(row_id, col_id),
-        )
+    }
+    Ok(())
+}
+
+fn part01(views: &[Vec<TreeView>]) -> usize {
+    views
+        .iter()
+        .flatten()
+        .filter(|view| view.is_visible())
+        .count()
+}
+
+fn part02(views: &[Vec<TreeView>]) -> usize {
+    views
+        .iter()
+        .flatten()
+        .map(TreeView::scenic_score)
         .max()
         .unwrap_or(0)
 }
 
 fn main() -> anyhow::Result<()> {
-    let lines = input_lines(INPUT)?;
-    let grid: Vec<Vec<u8>> = lines
-        .iter()
-        .map(|line| line.as_bytes().iter().map(|b| b - b'0').collect())
-        .collect();
-    assert!(!grid.is_empty() && grid.iter().all(|row| row.len() == grid[0].len()));
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let forest = Forest::try_from(lines.as_slice())?;
+    let views = forest.tree_views();
+
+    if let Some(heatmap) = opts.heatmap {
+        println!("{}", render_heatmap(&views, heatmap));
+    }
+    if let Some(path) = &opts.csv {
+        write_csv(&forest, &views, path)?;
+    }
 
-    println!("Part 1: {}", part01(&grid));
-    println!("Part 2: {}", part02(&grid));
+    println!("Part 1: {}", part01(&views));
+    println!("Part 2: {}", part02(&views));
     Ok(())
 }
@@ -1,11 +1,22 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
 use scan_fmt::scan_fmt;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
-use std::convert::TryFrom;
 use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
+
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Prints the rope after each move and the cells visited by its tail
+    #[arg(long)]
+    render: bool,
+
+    /// Additionally simulates a rope with the given number of knots, reporting the positions visited by each knot
+    #[arg(long)]
+    knots: Option<usize>,
+}
 impl std::fmt::Display for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "({},{})", self.row, self.column)
@@ -13,7 +24,7 @@ impl std::fmt::Display for Position {
 }
 impl std::fmt::Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}({})", self.direction, self.amount)
+        write!(f, "{} {}", self.direction.symbol(), self.amount)
     }
 }
 
@@ -23,23 +34,40 @@ enum Direction {
     Right,
     Down,
     Left,
-
-    // Internal directions for tail to follow head
     UpRight,
     UpLeft,
     DownRight,
     DownLeft,
 }
 
-impl TryFrom<char> for Direction {
-    type Error = anyhow::Error;
-    fn try_from(c: char) -> Result<Self, Self::Error> {
-        match c {
-            'U' => Ok(Self::Up),
-            'L' => Ok(Self::Left),
-            'D' => Ok(Self::Down),
-            'R' => Ok(Self::Right),
-            _ => Err(anyhow::anyhow!("Unrecognised direction '{c}'")),
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "U" => Ok(Self::Up),
+            "L" => Ok(Self::Left),
+            "D" => Ok(Self::Down),
+            "R" => Ok(Self::Right),
+            "UR" => Ok(Self::UpRight),
+            "UL" => Ok(Self::UpLeft),
+            "DR" => Ok(Self::DownRight),
+            "DL" => Ok(Self::DownLeft),
+            _ => Err(anyhow::anyhow!("Unrecognised direction '{s}'")),
+        }
+    }
+}
+
+impl Direction {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Up => "U",
+            Self::Right => "R",
+            Self::Down => "D",
+            Self::Left => "L",
+            Self::UpRight => "UR",
+            Self::UpLeft => "UL",
+            Self::DownRight => "DR",
+            Self::DownLeft => "DL",
         }
     }
 }
@@ -54,9 +82,9 @@ impl FromStr for Move {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (direction_c, amount) = scan_fmt!(s, "{} {}", char, usize)?;
+        let (direction, amount) = scan_fmt!(s, "{} {}", String, usize)?;
         Ok(Self {
-            direction: direction_c.try_into()?,
+            direction: direction.parse()?,
             amount,
         })
     }
@@ -125,45 +153,173 @@ impl Position {
     }
 }
 
-fn rope_movements(moves: &[Move], number_of_knots: usize) -> usize {
-    assert!(number_of_knots > 1);
+/// Rope made of knots, the first knot is the head and each knot follows the previous one
+#[derive(Debug)]
+struct Rope {
+    knots: Vec<Position>,
+    visited_cells: Vec<BTreeSet<Position>>,
+}
+
+impl Rope {
+    fn new(number_of_knots: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(number_of_knots > 0, "A rope has at least one knot");
+        Ok(Self {
+            knots: vec![Position::default(); number_of_knots],
+            visited_cells: vec![BTreeSet::from([Position::default()]); number_of_knots],
+        })
+    }
+
+    fn tail(&self) -> usize {
+        self.knots.len() - 1
+    }
+
+    /// Moves the head by one cell, letting the other knots follow it
+    fn step(&mut self, direction: Direction) {
+        self.knots[0].apply(direction);
+        self.visited_cells[0].insert(self.knots[0]);
+        for knot_index in 1..self.knots.len() {
+            if let Some(direction) = self.knots[knot_index].direction_to(self.knots[knot_index - 1])
+            {
+                self.knots[knot_index].apply(direction);
+                self.visited_cells[knot_index].insert(self.knots[knot_index]);
+            }
+        }
+    }
+
+    /// Applies all the moves, invoking `on_move` with the rope obtained after each move
+    fn run(&mut self, moves: &[Move], mut on_move: impl FnMut(&Move, &Self)) {
+        for move_ in moves {
+            for _ in 0..move_.amount {
+                self.step(move_.direction);
+            }
+            on_move(move_, self);
+        }
+    }
 
-    let mut visited_cells = BTreeSet::<Position>::new();
-    let mut knots: Vec<Position> = (0..number_of_knots).map(|_| Position::default()).collect();
+    fn visited_cells(&self, knot_index: usize) -> &BTreeSet<Position> {
+        &self.visited_cells[knot_index]
+    }
 
-    visited_cells.insert(Position::default());
+    fn knot_label(&self, knot_index: usize) -> char {
+        match knot_index {
+            0 => 'H',
+            1 if self.knots.len() == 2 => 'T',
+            1..=9 => char::from_digit(knot_index as u32, 10).unwrap_or('*'),
+            _ => '*',
+        }
+    }
 
-    for move_ in moves {
-        for _ in 0..move_.amount {
-            knots[0].apply(move_.direction);
-            for knot_index in 1..number_of_knots {
-                if let Some(direction) = knots[knot_index].direction_to(knots[knot_index - 1]) {
-                    knots[knot_index].apply(direction);
-                }
+    /// Renders the knots as in the assignment examples, within the given (top-left, bottom-right) bounds
+    fn render(&self, board_bounds: (Position, Position)) -> String {
+        render_board(board_bounds, |position| {
+            if let Some(knot_index) = self.knots.iter().position(|knot| knot == &position) {
+                self.knot_label(knot_index)
+            } else if position == Position::default() {
+                's'
+            } else {
+                '.'
+            }
+        })
+    }
 
-                visited_cells.insert(knots[number_of_knots - 1]);
+    /// Renders the cells visited by the knot, within the given (top-left, bottom-right) bounds
+    fn render_visited(&self, board_bounds: (Position, Position), knot_index: usize) -> String {
+        render_board(board_bounds, |position| {
+            if position == Position::default() {
+                's'
+            } else if self.visited_cells[knot_index].contains(&position) {
+                '#'
+            } else {
+                '.'
             }
+        })
+    }
+}
+
+fn render_board(
+    (top_left, bottom_right): (Position, Position),
+    cell: impl Fn(Position) -> char,
+) -> String {
+    let mut rendered = String::new();
+    for row in top_left.row..=bottom_right.row {
+        for column in top_left.column..=bottom_right.column {
+            rendered.push(cell(Position { row, column }));
         }
+        rendered.push('\n');
     }
-    visited_cells.len()
+    rendered
+}
+
+/// Smallest bounds containing all the given positions
+fn bounds<'a>(positions: impl Iterator<Item = &'a Position>) -> (Position, Position) {
+    positions.fold(
+        (Position::default(), Position::default()),
+        |(top_left, bottom_right), position| {
+            (
+                Position {
+                    row: top_left.row.min(position.row),
+                    column: top_left.column.min(position.column),
+                },
+                Position {
+                    row: bottom_right.row.max(position.row),
+                    column: bottom_right.column.max(position.column),
+                },
+            )
+        },
+    )
 }
 
-fn part01(moves: &[Move]) -> usize {
-    rope_movements(moves, 2)
+fn rope_movements(moves: &[Move], number_of_knots: usize, render: bool) -> anyhow::Result<usize> {
+    let mut rope = Rope::new(number_of_knots)?;
+    rope.run(moves, |_, _| {});
+
+    if render {
+        // Render once the whole run is known, so that every frame shares the same bounds
+        let board_bounds = bounds(rope.visited_cells.iter().flatten());
+        let mut replay = Rope::new(number_of_knots)?;
+        println!("== Initial State ==\n\n{}", replay.render(board_bounds));
+        replay.run(moves, |move_, rope| {
+            println!("== {} ==\n\n{}", move_, rope.render(board_bounds));
+        });
+        println!(
+            "== Visited by {} ==\n\n{}",
+            rope.knot_label(rope.tail()),
+            rope.render_visited(board_bounds, rope.tail())
+        );
+    }
+
+    Ok(rope.visited_cells(rope.tail()).len())
 }
 
-fn part02(moves: &[Move]) -> usize {
-    rope_movements(moves, 10)
+fn part01(moves: &[Move], render: bool) -> anyhow::Result<usize> {
+    rope_movements(moves, 2, render)
+}
+
+fn part02(moves: &[Move], render: bool) -> anyhow::Result<usize> {
+    rope_movements(moves, 10, render)
 }
 
 fn main() -> anyhow::Result<()> {
-    let moves: Vec<Move> = input_lines(INPUT)?
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let moves: Vec<Move> = lines
         .iter()
         .map(|line| line.parse())
         .collect::<Result<_, _>>()?;
 
-    println!("Part 1: {}", part01(&moves));
-    println!("Part 2: {}", part02(&moves));
+    println!("Part 1: {}", part01(&moves, opts.render)?);
+    println!("Part 2: {}", part02(&moves, opts.render)?);
+
+    if let Some(number_of_knots) = opts.knots {
+        let mut rope = Rope::new(number_of_knots)?;
+        rope.run(&moves, |_, _| {});
+        for knot_index in 0..number_of_knots {
+            println!(
+                "Knot {} visited {} positions",
+                rope.knot_label(knot_index),
+                rope.visited_cells(knot_index).len()
+            );
+        }
+    }
 
     Ok(())
 }
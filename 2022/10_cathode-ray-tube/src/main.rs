@@ -1,9 +1,22 @@
-use helpers::input_lines;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use helpers::ocr::read_letters;
 use std::collections::BTreeSet;
 use std::str::FromStr;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Number of rows of the CRT screen
+    #[arg(long, default_value_t = 6)]
+    screen_rows: usize,
+
+    /// Number of columns of the CRT screen
+    #[arg(long, default_value_t = 40)]
+    screen_columns: usize,
+}
+
 #[derive(Debug)]
 enum Instruction {
     Noop,
@@ -62,57 +75,127 @@ impl Grid {
         }
     }
 }
+#[derive(Clone, Copy, Debug)]
+struct Registers {
+    x: i64,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self { x: 1 }
+    }
+}
+
+impl Instruction {
+    fn cycles(&self) -> usize {
+        match self {
+            Self::Noop => 1,
+            Self::Addx(_) => 2,
+        }
+    }
+
+    fn execute(&self, registers: &mut Registers) {
+        match self {
+            Self::Noop => {}
+            Self::Addx(v) => registers.x += v,
+        }
+    }
+}
+
+/// Hook invoked during each CPU cycle, before the running instruction completes
+trait CycleObserver {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers);
+}
+
+#[derive(Debug, Default)]
+struct Cpu {
+    registers: Registers,
+    cycle: usize,
+}
+
+impl Cpu {
+    fn run(&mut self, instructions: &[Instruction], observer: &mut impl CycleObserver) {
+        for instruction in instructions {
+            for _ in 0..instruction.cycles() {
+                self.cycle += 1;
+                observer.on_cycle(self.cycle, &self.registers);
+            }
+            instruction.execute(&mut self.registers);
+        }
+    }
+}
+
+/// Sums the signal strength (cycle times `X` register) during the sampled cycles
+#[derive(Debug)]
+struct SignalStrength {
+    sampled_cycles: &'static [usize],
+    total: i64,
+}
+
+impl CycleObserver for SignalStrength {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers) {
+        if self.sampled_cycles.contains(&cycle) {
+            self.total += (cycle as i64) * registers.x;
+        }
+    }
+}
+
+/// Draws a pixel per cycle, lit if the 3 pixels wide sprite centered on the `X` register overlaps it
+#[derive(Debug)]
+struct Crt {
+    grid: Grid,
+}
+
+impl CycleObserver for Crt {
+    fn on_cycle(&mut self, cycle: usize, registers: &Registers) {
+        let row = (cycle - 1) / self.grid.n_columns;
+        let column = (cycle - 1) % self.grid.n_columns;
+        if row < self.grid.n_rows && (registers.x - column as i64).abs() <= 1 {
+            self.grid.lit_cells.insert((row, column));
+        }
+    }
+}
+
 fn part01(instructions: &[Instruction]) -> i64 {
-    static IMPORTANT_TIMES: &[i64] = &[20, 60, 100, 140, 180, 220];
-    instructions
-        .iter()
-        .flat_map(|instruction| match instruction {
-            Instruction::Noop => vec![0],
-            Instruction::Addx(v) => vec![0, *v],
-        })
-        .enumerate()
-        .fold((1, 0), |(register_value, score), (clock_tick, to_add)| {
-            (
-                register_value + to_add,
-                if IMPORTANT_TIMES.contains(&((clock_tick as i64) + 1)) {
-                    score + ((clock_tick as i64) + 1) * register_value
-                } else {
-                    score
-                },
-            )
-        })
-        .1
+    let mut signal_strength = SignalStrength {
+        sampled_cycles: &[20, 60, 100, 140, 180, 220],
+        total: 0,
+    };
+    Cpu::default().run(instructions, &mut signal_strength);
+    signal_strength.total
 }
 
-fn part02(instructions: &[Instruction]) -> Grid {
-    instructions
-        .iter()
-        .flat_map(|instruction| match instruction {
-            Instruction::Noop => vec![0],
-            Instruction::Addx(v) => vec![0, *v],
-        })
-        .enumerate()
-        .fold(
-            (1, Grid::new(6, 40)),
-            |(position, mut grid), (clock_tick, to_add)| {
-                let row = clock_tick / grid.n_columns;
-                let column = clock_tick % grid.n_columns;
-                if (position - column as i64).abs() <= 1 {
-                    grid.lit_cells.insert((row, column));
-                }
-                (position + to_add, grid)
-            },
-        )
-        .1
+fn part02(instructions: &[Instruction], n_rows: usize, n_columns: usize) -> Grid {
+    let mut crt = Crt {
+        grid: Grid::new(n_rows, n_columns),
+    };
+    Cpu::default().run(instructions, &mut crt);
+    crt.grid
 }
 
 fn main() -> anyhow::Result<()> {
-    let instructions: Vec<Instruction> = input_lines(INPUT)?
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let instructions: Vec<Instruction> = lines
         .iter()
         .map(|line| line.parse())
         .collect::<Result<_, _>>()?;
 
     println!("Part 1: {}", part01(&instructions));
-    println!("Part 2:\n{}", part02(&instructions));
+    anyhow::ensure!(
+        opts.screen_columns > 0,
+        "The CRT screen should have at least one column"
+    );
+    let grid = part02(&instructions, opts.screen_rows, opts.screen_columns);
+    match read_letters(
+        grid.lit_cells
+            .iter()
+            .map(|(row, column)| (*row as isize, *column as isize)),
+    ) {
+        Ok(letters) => println!("Part 2: {}", letters),
+        Err(err) => {
+            eprintln!("{}", err);
+            println!("Part 2:\n{}", grid);
+        }
+    }
     Ok(())
 }
@@ -1,9 +1,17 @@
-use helpers::input_lines;
-use pathfinding::prelude::dijkstra;
+use clap::Args;
+use helpers::input_lines_with_opts;
+use std::collections::VecDeque;
 use std::convert::TryFrom;
 
 const INPUT: &str = include_str!("../input.txt");
 
+#[derive(Args, Debug)]
+struct ExtraOpts {
+    /// Draws the shortest routes over the heightmap
+    #[arg(long)]
+    route: bool,
+}
+
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 struct Position {
     row: usize,
@@ -102,108 +110,161 @@ impl std::fmt::Display for Input {
     }
 }
 
-fn shortest_path_length(input: &Input, start: &Position, end: &Position) -> Option<usize> {
-    dijkstra(
-        /* start */ start,
-        /* successors */
-        |position| {
-            let mut successors = vec![];
-            if position.row > 0
-                && input.map[position.row - 1][position.column]
-                    <= input.map[position.row][position.column] + 1
-            {
-                // Up is possible
-                successors.push((
-                    Position {
-                        row: position.row - 1,
-                        column: position.column,
-                    },
-                    1,
-                ));
-            }
-            if position.row < input.n_rows - 1
-                && input.map[position.row + 1][position.column]
-                    <= input.map[position.row][position.column] + 1
-            {
-                // Down is possible
-                successors.push((
-                    Position {
-                        row: position.row + 1,
-                        column: position.column,
-                    },
-                    1,
-                ));
-            }
+impl Position {
+    fn neighbours(self, n_rows: usize, n_columns: usize) -> impl Iterator<Item = Self> {
+        let Self { row, column } = self;
+        [
+            (row > 0).then(|| Self {
+                row: row - 1,
+                column,
+            }),
+            (row + 1 < n_rows).then_some(Self {
+                row: row + 1,
+                column,
+            }),
+            (column > 0).then(|| Self {
+                row,
+                column: column - 1,
+            }),
+            (column + 1 < n_columns).then_some(Self {
+                row,
+                column: column + 1,
+            }),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
 
-            if position.column > 0
-                && input.map[position.row][position.column - 1]
-                    <= input.map[position.row][position.column] + 1
-            {
-                // Left is possible
-                successors.push((
-                    Position {
-                        row: position.row,
-                        column: position.column - 1,
-                    },
-                    1,
-                ));
-            }
-            if position.column < input.n_columns - 1
-                && input.map[position.row][position.column + 1]
-                    <= input.map[position.row][position.column] + 1
-            {
-                // Right is possible
-                successors.push((
-                    Position {
-                        row: position.row,
-                        column: position.column + 1,
-                    },
-                    1,
-                ));
+impl Input {
+    fn elevation(&self, position: Position) -> u8 {
+        self.map[position.row][position.column]
+    }
+
+    fn can_climb(&self, from: Position, to: Position) -> bool {
+        self.elevation(to) <= self.elevation(from) + 1
+    }
+
+    fn positions_with_elevation(&self, elevation: u8) -> impl Iterator<Item = Position> + '_ {
+        (0..self.n_rows)
+            .flat_map(move |row| (0..self.n_columns).map(move |column| Position { row, column }))
+            .filter(move |position| self.elevation(*position) == elevation)
+    }
+
+    /// Shortest distances from every cell to the end, via a single BFS from the end
+    /// that walks the climbing rule backwards
+    fn distances_to_end(&self) -> DistancesToEnd {
+        let mut distances = vec![vec![None; self.n_columns]; self.n_rows];
+        let mut next_steps = vec![vec![None; self.n_columns]; self.n_rows];
+        let mut queue = VecDeque::from([self.end]);
+        distances[self.end.row][self.end.column] = Some(0);
+
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[position.row][position.column].unwrap_or_default();
+            for previous in position.neighbours(self.n_rows, self.n_columns) {
+                if distances[previous.row][previous.column].is_none()
+                    && self.can_climb(previous, position)
+                {
+                    distances[previous.row][previous.column] = Some(distance + 1);
+                    next_steps[previous.row][previous.column] = Some(position);
+                    queue.push_back(previous);
+                }
             }
+        }
+
+        DistancesToEnd {
+            distances,
+            next_steps,
+        }
+    }
 
-            // neighbours
-            successors
-        },
-        /* success */ |position| end == position,
-    )
-    .map(|(_, cost)| cost)
+    /// Draws the route over the heightmap, as in the assignment
+    fn render_route(&self, route: &[Position]) -> String {
+        let mut canvas = vec![vec!['.'; self.n_columns]; self.n_rows];
+        for step in route.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            canvas[from.row][from.column] = if to.row > from.row {
+                'v'
+            } else if to.row < from.row {
+                '^'
+            } else if to.column > from.column {
+                '>'
+            } else {
+                '<'
+            };
+        }
+        canvas[self.end.row][self.end.column] = 'E';
+        canvas
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-fn part01(input: &Input) -> usize {
-    shortest_path_length(input, &input.start, &input.end).unwrap_or(0)
+#[derive(Debug)]
+struct DistancesToEnd {
+    distances: Vec<Vec<Option<usize>>>,
+    /// Next cell of a shortest route towards the end
+    next_steps: Vec<Vec<Option<Position>>>,
+}
+
+impl DistancesToEnd {
+    fn distance(&self, position: Position) -> Option<usize> {
+        self.distances[position.row][position.column]
+    }
+
+    /// Shortest route from `start` to the end (both included)
+    fn route(&self, start: Position) -> Option<Vec<Position>> {
+        self.distance(start)?;
+        let mut route = vec![start];
+        let mut position = start;
+        while let Some(next) = self.next_steps[position.row][position.column] {
+            route.push(next);
+            position = next;
+        }
+        Some(route)
+    }
 }
 
-fn part02(input: &Input) -> usize {
+/// Best starting position among the lowest cells
+fn best_lowest_start(input: &Input, distances: &DistancesToEnd) -> Option<Position> {
     input
-        .map
-        .iter()
-        .enumerate()
-        .flat_map(|(row_id, row)| {
-            row.iter()
-                .enumerate()
-                .map(move |(column_id, cell)| (row_id, column_id, cell))
-        })
-        .filter_map(|(row_id, column_id, cell)| {
-            if *cell == b'a' {
-                Some(Position {
-                    row: row_id,
-                    column: column_id,
-                })
-            } else {
-                None
-            }
-        })
-        .filter_map(|start_position| shortest_path_length(input, &start_position, &input.end))
-        .min()
+        .positions_with_elevation(b'a')
+        .filter(|position| distances.distance(*position).is_some())
+        .min_by_key(|position| distances.distance(*position))
+}
+
+fn part01(input: &Input, distances: &DistancesToEnd) -> usize {
+    distances.distance(input.start).unwrap_or(0)
+}
+
+fn part02(input: &Input, distances: &DistancesToEnd) -> usize {
+    best_lowest_start(input, distances)
+        .and_then(|start| distances.distance(start))
         .unwrap_or(0)
 }
 
 fn main() -> anyhow::Result<()> {
-    let input = Input::try_from(&input_lines(INPUT)?)?;
+    let (lines, opts) = input_lines_with_opts::<ExtraOpts>(INPUT)?;
+    let input = Input::try_from(&lines)?;
+    let distances = input.distances_to_end();
+
+    if opts.route {
+        let starts = [Some(input.start), best_lowest_start(&input, &distances)];
+        for (part, start) in starts.iter().enumerate() {
+            if let Some(route) = start.and_then(|start| distances.route(start)) {
+                println!(
+                    "Route of part {}:\n{}\n",
+                    part + 1,
+                    input.render_route(&route)
+                );
+            }
+        }
+    }
 
-    println!("Part 1: {}", part01(&input));
-    println!("Part 2: {}", part02(&input));
+    println!("Part 1: {}", part01(&input, &distances));
+    println!("Part 2: {}", part02(&input, &distances));
 
     Ok(())
 }
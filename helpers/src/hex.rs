@@ -0,0 +1,321 @@
+//! Hexagonal grids.
+//!
+//! Tiles are identified by axial coordinates `(q, r)`, which can be converted into cube
+//! coordinates (handy for distances and rotations) and into offset coordinates (the row/column
+//! layout of a drawn grid). Axial coordinates do not depend on the orientation of the tiles,
+//! the [`Orientation`] only affects the names of the directions, the offset layout and the rendering.
+
+use std::collections::HashSet;
+
+/// Axial coordinates of a tile
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Axial {
+    pub q: i64,
+    pub r: i64,
+}
+
+impl std::ops::Add for Axial {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            q: self.q + rhs.q,
+            r: self.r + rhs.r,
+        }
+    }
+}
+
+impl std::ops::Sub for Axial {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            q: self.q - rhs.q,
+            r: self.r - rhs.r,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Axial {
+    fn add_assign(&mut self, rhs: Self) {
+        self.q += rhs.q;
+        self.r += rhs.r;
+    }
+}
+
+impl std::ops::Mul<i64> for Axial {
+    type Output = Self;
+    fn mul(self, rhs: i64) -> Self {
+        Self {
+            q: self.q * rhs,
+            r: self.r * rhs,
+        }
+    }
+}
+
+impl Axial {
+    /// Unit steps towards the six neighbours, in counterclockwise order starting from `+q`
+    pub const DIRECTIONS: [Self; 6] = [
+        Self { q: 1, r: 0 },
+        Self { q: 1, r: -1 },
+        Self { q: 0, r: -1 },
+        Self { q: -1, r: 0 },
+        Self { q: -1, r: 1 },
+        Self { q: 0, r: 1 },
+    ];
+
+    #[must_use]
+    pub fn new(q: i64, r: i64) -> Self {
+        Self { q, r }
+    }
+
+    pub fn neighbours(self) -> impl Iterator<Item = Self> {
+        Self::DIRECTIONS
+            .into_iter()
+            .map(move |direction| self + direction)
+    }
+
+    /// Number of steps needed to reach `other`
+    #[must_use]
+    pub fn distance(self, other: Self) -> u64 {
+        let delta = other - self;
+        (delta.q.unsigned_abs() + delta.r.unsigned_abs() + (delta.q + delta.r).unsigned_abs()) / 2
+    }
+
+    /// Tiles at exactly `radius` steps, walking counterclockwise
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `radius` does not fit in `i64`.
+    #[must_use]
+    pub fn ring(self, radius: u64) -> Vec<Self> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let radius = i64::try_from(radius).expect("Radius is expected to fit in i64");
+        let mut tile = self + Self::DIRECTIONS[4] * radius;
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        for direction in Self::DIRECTIONS {
+            for _ in 0..radius {
+                ring.push(tile);
+                tile += direction;
+            }
+        }
+        ring
+    }
+
+    /// Tiles within `radius` steps, ring after ring starting from the center
+    #[must_use]
+    pub fn spiral(self, radius: u64) -> Vec<Self> {
+        (0..=radius).flat_map(|radius| self.ring(radius)).collect()
+    }
+
+    /// Offset coordinates in the layout where every other row (pointy orientation)
+    /// or column (flat orientation) is shifted
+    #[must_use]
+    pub fn to_offset(self, orientation: Orientation, parity: Parity) -> Offset {
+        match orientation {
+            Orientation::Pointy => Offset {
+                col: self.q + parity.shift(self.r),
+                row: self.r,
+            },
+            Orientation::Flat => Offset {
+                col: self.q,
+                row: self.r + parity.shift(self.q),
+            },
+        }
+    }
+}
+
+/// Cube coordinates of a tile, where `x + y + z == 0`
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Cube {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl Cube {
+    /// Creates the coordinates, if they lie on the `x + y + z == 0` plane
+    #[must_use]
+    pub fn new(x: i64, y: i64, z: i64) -> Option<Self> {
+        (x + y + z == 0).then_some(Self { x, y, z })
+    }
+
+    /// Number of steps needed to reach `other`
+    #[must_use]
+    pub fn distance(self, other: Self) -> u64 {
+        (other.x - self.x)
+            .unsigned_abs()
+            .max((other.y - self.y).unsigned_abs())
+            .max((other.z - self.z).unsigned_abs())
+    }
+
+    /// Rotation by 60 degrees counterclockwise around the origin
+    #[must_use]
+    pub fn rotate_left(self) -> Self {
+        Self {
+            x: -self.y,
+            y: -self.z,
+            z: -self.x,
+        }
+    }
+
+    /// Rotation by 60 degrees clockwise around the origin
+    #[must_use]
+    pub fn rotate_right(self) -> Self {
+        Self {
+            x: -self.z,
+            y: -self.x,
+            z: -self.y,
+        }
+    }
+}
+
+impl From<Axial> for Cube {
+    fn from(axial: Axial) -> Self {
+        Self {
+            x: axial.q,
+            y: -axial.q - axial.r,
+            z: axial.r,
+        }
+    }
+}
+
+impl From<Cube> for Axial {
+    fn from(cube: Cube) -> Self {
+        Self {
+            q: cube.x,
+            r: cube.z,
+        }
+    }
+}
+
+/// Which rows (or columns) are shifted in an offset layout
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Parity {
+    Odd,
+    Even,
+}
+
+impl Parity {
+    /// Shift of the row (or column) `line` with respect to the axial coordinate
+    fn shift(self, line: i64) -> i64 {
+        match self {
+            Self::Odd => (line - (line & 1)) / 2,
+            Self::Even => (line + (line & 1)) / 2,
+        }
+    }
+}
+
+/// Offset coordinates of a tile, as row and column of a drawn grid
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Offset {
+    pub col: i64,
+    pub row: i64,
+}
+
+impl Offset {
+    #[must_use]
+    pub fn to_axial(self, orientation: Orientation, parity: Parity) -> Axial {
+        match orientation {
+            Orientation::Pointy => Axial {
+                q: self.col - parity.shift(self.row),
+                r: self.row,
+            },
+            Orientation::Flat => Axial {
+                q: self.col,
+                r: self.row - parity.shift(self.col),
+            },
+        }
+    }
+}
+
+/// Orientation of the tiles
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Orientation {
+    /// Tiles with a vertex on top, neighbours are `e`, `ne`, `nw`, `w`, `sw` and `se`
+    Pointy,
+    /// Tiles with a side on top, neighbours are `se`, `ne`, `n`, `nw`, `sw` and `s`
+    Flat,
+}
+
+impl Orientation {
+    /// Names of the directions, in the same order as [`Axial::DIRECTIONS`]
+    #[must_use]
+    pub fn direction_names(self) -> [&'static str; 6] {
+        match self {
+            Self::Pointy => ["e", "ne", "nw", "w", "sw", "se"],
+            Self::Flat => ["se", "ne", "n", "nw", "sw", "s"],
+        }
+    }
+
+    /// Parses a sequence of direction names without separators (ie. `esenee`)
+    /// into the corresponding unit steps
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the path contains something that is not a direction name.
+    pub fn parse_path(self, path: &str) -> anyhow::Result<Vec<Axial>> {
+        let names = self.direction_names();
+        let mut residual = path;
+        let mut steps = vec![];
+        while !residual.is_empty() {
+            let (name, direction) = names
+                .iter()
+                .zip(Axial::DIRECTIONS)
+                .filter(|(name, _)| residual.starts_with(**name))
+                .max_by_key(|(name, _)| name.len())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unexpected direction at position {} of '{}'",
+                        path.len() - residual.len(),
+                        path
+                    )
+                })?;
+            steps.push(direction);
+            residual = &residual[name.len()..];
+        }
+        Ok(steps)
+    }
+}
+
+/// Draws the bounding box of `tiles`, using `symbol` for every tile of the box.
+///
+/// Pointy tiles are drawn in staggered rows, flat tiles in staggered columns.
+pub fn render<F: Fn(Axial) -> char>(
+    tiles: &HashSet<Axial>,
+    orientation: Orientation,
+    symbol: F,
+) -> String {
+    // In "doubled" coordinates neighbouring tiles in the staggered direction are 2 cells apart
+    let doubled = |tile: &Axial| match orientation {
+        Orientation::Pointy => (tile.r, 2 * tile.q + tile.r),
+        Orientation::Flat => (2 * tile.r + tile.q, tile.q),
+    };
+    let (Some(min_row), Some(max_row)) = (
+        tiles.iter().map(|tile| doubled(tile).0).min(),
+        tiles.iter().map(|tile| doubled(tile).0).max(),
+    ) else {
+        return String::new();
+    };
+    let min_col = tiles.iter().map(|tile| doubled(tile).1).min().unwrap_or(0);
+    let max_col = tiles.iter().map(|tile| doubled(tile).1).max().unwrap_or(0);
+
+    (min_row..=max_row)
+        .map(|row| {
+            (min_col..=max_col)
+                .map(|col| {
+                    if (row + col) % 2 != 0 {
+                        return ' ';
+                    }
+                    match orientation {
+                        Orientation::Pointy => symbol(Axial::new((col - row) / 2, row)),
+                        Orientation::Flat => symbol(Axial::new(col, (row - col) / 2)),
+                    }
+                })
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
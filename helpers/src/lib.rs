@@ -1,20 +1,31 @@
-use clap::Parser;
+use clap::{Args, Parser};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+pub mod hex;
+pub mod matching;
+pub mod ocr;
+pub mod transition;
+
 #[derive(Parser, Debug)]
-struct Opts {
+struct Opts<T: Args> {
     /// Sets a custom input file for the assignment. Passing no value implies the usage of the "official" assignment input.
     // #[clap(short, long)]
     input_filename: Option<String>,
+
+    #[command(flatten)]
+    extra: T,
 }
 
+/// Placeholder for assignments that do not define any additional CLI argument.
+#[derive(Args, Debug)]
+struct NoExtraOpts {}
+
 fn read_lines(filename: &str) -> std::io::Result<Box<dyn Iterator<Item = String>>> {
     Ok(Box::new(
         BufReader::new(File::open(filename)?)
             .lines()
-            .into_iter()
-            .filter_map(Result::ok),
+            .map_while(Result::ok),
     ))
 }
 
@@ -23,7 +34,19 @@ fn read_lines(filename: &str) -> std::io::Result<Box<dyn Iterator<Item = String>
 /// Will return `Err` if the `filename` provided in the CLI argumens does not exist
 /// or the user does not have permission to read it.
 pub fn input_lines(input: &str) -> anyhow::Result<Vec<String>> {
-    let mut res: Vec<String> = match Opts::parse().input_filename.as_deref() {
+    let (lines, _) = input_lines_with_opts::<NoExtraOpts>(input)?;
+    Ok(lines)
+}
+
+/// Same as [`input_lines`], but allows the assignment to define additional CLI arguments.
+///
+/// # Errors
+///
+/// Will return `Err` if the `filename` provided in the CLI argumens does not exist
+/// or the user does not have permission to read it.
+pub fn input_lines_with_opts<T: Args>(input: &str) -> anyhow::Result<(Vec<String>, T)> {
+    let opts = Opts::<T>::parse();
+    let mut res: Vec<String> = match opts.input_filename.as_deref() {
         Some(path) => {
             eprintln!("Loading input from custom file: {}", &path);
             read_lines(path)?.collect()
@@ -34,5 +57,5 @@ pub fn input_lines(input: &str) -> anyhow::Result<Vec<String>> {
     if res[res.len() - 1].is_empty() {
         res.remove(res.len() - 1);
     }
-    Ok(res)
+    Ok((res, opts.extra))
 }
@@ -0,0 +1,207 @@
+//! Maximum matchings on bipartite graphs.
+//!
+//! Useful for assignments where each item (left vertex) has to be associated to a distinct
+//! candidate (right vertex), reporting why no assignment or multiple assignments exist.
+
+use std::collections::{BTreeSet, VecDeque};
+
+/// Matching between the left and the right vertices of a bipartite graph
+#[derive(Clone, Debug)]
+pub struct Matching {
+    left_to_right: Vec<Option<usize>>,
+    right_to_left: Vec<Option<usize>>,
+}
+
+impl Matching {
+    /// Number of matched pairs
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.left_to_right.iter().flatten().count()
+    }
+
+    /// Right vertex matched with `left`, if any
+    #[must_use]
+    pub fn right_of(&self, left: usize) -> Option<usize> {
+        self.left_to_right.get(left).copied().flatten()
+    }
+
+    /// First left vertex that is not matched, if any
+    #[must_use]
+    pub fn unmatched_left(&self) -> Option<usize> {
+        self.left_to_right.iter().position(Option::is_none)
+    }
+
+    fn assign(&mut self, left: usize, right: usize) {
+        self.left_to_right[left] = Some(right);
+        self.right_to_left[right] = Some(left);
+    }
+}
+
+/// Bipartite graph with edges from the left vertices to the right ones
+#[derive(Debug)]
+pub struct BipartiteGraph {
+    adjacency: Vec<Vec<usize>>,
+    right_count: usize,
+}
+
+impl BipartiteGraph {
+    /// Creates the graph from the right neighbours of each left vertex
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any neighbour is not smaller than `right_count`.
+    #[must_use]
+    pub fn new(adjacency: Vec<Vec<usize>>, right_count: usize) -> Self {
+        assert!(
+            adjacency.iter().flatten().all(|right| *right < right_count),
+            "Right vertex out of bounds"
+        );
+        Self {
+            adjacency,
+            right_count,
+        }
+    }
+
+    #[must_use]
+    pub fn left_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    #[must_use]
+    pub fn neighbours(&self, left: usize) -> &[usize] {
+        &self.adjacency[left]
+    }
+
+    /// Maximum matching computed via Hopcroft–Karp
+    #[must_use]
+    pub fn maximum_matching(&self) -> Matching {
+        let mut matching = Matching {
+            left_to_right: vec![None; self.adjacency.len()],
+            right_to_left: vec![None; self.right_count],
+        };
+
+        loop {
+            // Layer the left vertices by length of the shortest alternating path from a free left vertex
+            let mut layers = vec![usize::MAX; self.adjacency.len()];
+            let mut queue = VecDeque::new();
+            for (left, right) in matching.left_to_right.iter().enumerate() {
+                if right.is_none() {
+                    layers[left] = 0;
+                    queue.push_back(left);
+                }
+            }
+
+            let mut found_augmenting_path = false;
+            while let Some(left) = queue.pop_front() {
+                for right in &self.adjacency[left] {
+                    match matching.right_to_left[*right] {
+                        None => found_augmenting_path = true,
+                        Some(next) if layers[next] == usize::MAX => {
+                            layers[next] = layers[left] + 1;
+                            queue.push_back(next);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if !found_augmenting_path {
+                return matching;
+            }
+
+            for left in 0..self.adjacency.len() {
+                if matching.left_to_right[left].is_none() {
+                    self.augment_along_layers(left, &mut layers, &mut matching);
+                }
+            }
+        }
+    }
+
+    fn augment_along_layers(
+        &self,
+        left: usize,
+        layers: &mut [usize],
+        matching: &mut Matching,
+    ) -> bool {
+        for right in &self.adjacency[left] {
+            let augmented = match matching.right_to_left[*right] {
+                None => true,
+                Some(next) => {
+                    layers[next] == layers[left] + 1
+                        && self.augment_along_layers(next, layers, matching)
+                }
+            };
+            if augmented {
+                matching.assign(left, *right);
+                return true;
+            }
+        }
+        // No augmenting path goes through this vertex anymore
+        layers[left] = usize::MAX;
+        false
+    }
+
+    /// Searches an augmenting path from `left` that does not use the `forbidden` edge
+    fn augment_avoiding(
+        &self,
+        left: usize,
+        forbidden: (usize, usize),
+        visited: &mut [bool],
+        matching: &mut Matching,
+    ) -> bool {
+        for right in &self.adjacency[left] {
+            if (left, *right) == forbidden || visited[*right] {
+                continue;
+            }
+            visited[*right] = true;
+            if matching.right_to_left[*right]
+                .is_none_or(|next| self.augment_avoiding(next, forbidden, visited, matching))
+            {
+                matching.assign(left, *right);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns a matching of the same size as `matching` where `left` is assigned
+    /// to a different right vertex, if any
+    #[must_use]
+    pub fn alternative_matching(&self, matching: &Matching, left: usize) -> Option<Matching> {
+        let right = matching.left_to_right[left]?;
+        let mut alternative = matching.clone();
+        alternative.left_to_right[left] = None;
+        alternative.right_to_left[right] = None;
+        let mut visited = vec![false; self.right_count];
+        if self.augment_avoiding(left, (left, right), &mut visited, &mut alternative) {
+            Some(alternative)
+        } else {
+            None
+        }
+    }
+
+    /// Given a maximum matching that leaves `left` unmatched, returns a set of left vertices
+    /// together with their neighbours, which are less than the left vertices (Hall's condition violation)
+    #[must_use]
+    pub fn hall_violator(
+        &self,
+        matching: &Matching,
+        left: usize,
+    ) -> (BTreeSet<usize>, BTreeSet<usize>) {
+        let mut lefts = BTreeSet::from([left]);
+        let mut rights = BTreeSet::new();
+        let mut queue = VecDeque::from([left]);
+        while let Some(left) = queue.pop_front() {
+            for right in &self.adjacency[left] {
+                if rights.insert(*right) {
+                    // The matching is maximum, so every reachable right vertex is matched
+                    if let Some(next) = matching.right_to_left[*right] {
+                        if lefts.insert(next) {
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+        (lefts, rights)
+    }
+}
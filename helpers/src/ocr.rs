@@ -0,0 +1,230 @@
+//! Recognition of the letters drawn by "read the screen" assignments.
+//!
+//! Advent of Code renders letters with two fonts: a small one (4 columns and 6 rows per glyph)
+//! and a large one (6 columns and 10 rows per glyph). The font is detected from the height of the
+//! bounding box of the lit cells.
+
+use std::collections::{BTreeSet, HashMap};
+
+const SMALL_FONT_HEIGHT: usize = 6;
+const SMALL_FONT: &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', ".###\n..#.\n..#.\n..#.\n..#.\n.###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+const LARGE_FONT_HEIGHT: usize = 10;
+const LARGE_FONT: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'B',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+    ),
+    (
+        'C',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+    ),
+    (
+        'E',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'F',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'G',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+    ),
+    (
+        'H',
+        "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'J',
+        "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+    ),
+    (
+        'K',
+        "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+    ),
+    (
+        'L',
+        "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'N',
+        "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+    ),
+    (
+        'P',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'R',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+    ),
+    (
+        'X',
+        "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+    ),
+    (
+        'Z',
+        "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+    ),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// No cell is lit
+    Empty,
+    /// The height of the lit cells does not match any known font
+    UnsupportedHeight(usize),
+    /// Some glyphs do not match any letter of the font. Glyphs are rendered with `#` and `.`
+    UnknownGlyphs(Vec<String>),
+}
+
+impl std::fmt::Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "No lit cells to recognise"),
+            Self::UnsupportedHeight(height) => write!(
+                f,
+                "Letters are {} rows high, supported fonts are {} and {} rows high",
+                height, SMALL_FONT_HEIGHT, LARGE_FONT_HEIGHT
+            ),
+            Self::UnknownGlyphs(glyphs) => {
+                write!(f, "Unable to recognise {} glyphs:", glyphs.len())?;
+                for glyph in glyphs {
+                    write!(f, "\n\n{}", glyph)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Renders the glyph, removing the empty columns on its sides
+fn render_glyph(rows: &[Vec<bool>]) -> String {
+    let first_column = rows
+        .iter()
+        .filter_map(|row| row.iter().position(|lit| *lit))
+        .min()
+        .unwrap_or(0);
+    let last_column = rows
+        .iter()
+        .filter_map(|row| row.iter().rposition(|lit| *lit))
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|row| {
+            row[first_column..=last_column]
+                .iter()
+                .map(|lit| if *lit { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn font_for_height(height: usize) -> Result<HashMap<String, char>, OcrError> {
+    let font = match height {
+        SMALL_FONT_HEIGHT => SMALL_FONT,
+        LARGE_FONT_HEIGHT => LARGE_FONT,
+        _ => return Err(OcrError::UnsupportedHeight(height)),
+    };
+    Ok(font
+        .iter()
+        .map(|(letter, glyph)| {
+            let rows: Vec<Vec<bool>> = glyph
+                .lines()
+                .map(|line| line.chars().map(|c| c == '#').collect())
+                .collect();
+            (render_glyph(&rows), *letter)
+        })
+        .collect())
+}
+
+/// Recognises the letters drawn by the lit `(row, column)` cells.
+///
+/// The cells are normalised to their bounding box, so any offset is accepted.
+/// Glyphs are separated by the columns without lit cells.
+///
+/// # Errors
+///
+/// Will return `Err` if the cells do not have the height of a known font or
+/// if some glyphs are not recognised.
+pub fn read_letters<I: IntoIterator<Item = (isize, isize)>>(lit: I) -> Result<String, OcrError> {
+    let lit: BTreeSet<(isize, isize)> = lit.into_iter().collect();
+    let (min_row, max_row, min_column, max_column) = lit
+        .iter()
+        .fold(None, |acc, (row, column)| {
+            let (min_row, max_row, min_column, max_column) =
+                acc.unwrap_or((*row, *row, *column, *column));
+            Some((
+                min_row.min(*row),
+                max_row.max(*row),
+                min_column.min(*column),
+                max_column.max(*column),
+            ))
+        })
+        .ok_or(OcrError::Empty)?;
+
+    let height = (max_row - min_row + 1) as usize;
+    let width = (max_column - min_column + 1) as usize;
+    let font = font_for_height(height)?;
+
+    let mut bitmap = vec![vec![false; width]; height];
+    for (row, column) in &lit {
+        bitmap[(row - min_row) as usize][(column - min_column) as usize] = true;
+    }
+
+    let mut letters = String::new();
+    let mut unknown_glyphs = Vec::new();
+    let mut column = 0;
+    while column < width {
+        if bitmap.iter().all(|row| !row[column]) {
+            column += 1;
+            continue;
+        }
+        let glyph_start = column;
+        while column < width && bitmap.iter().any(|row| row[column]) {
+            column += 1;
+        }
+        let glyph: Vec<Vec<bool>> = bitmap
+            .iter()
+            .map(|row| row[glyph_start..column].to_vec())
+            .collect();
+        let rendered_glyph = render_glyph(&glyph);
+        match font.get(&rendered_glyph) {
+            Some(letter) => letters.push(*letter),
+            None => unknown_glyphs.push(rendered_glyph),
+        }
+    }
+
+    if unknown_glyphs.is_empty() {
+        Ok(letters)
+    } else {
+        Err(OcrError::UnknownGlyphs(unknown_glyphs))
+    }
+}
@@ -0,0 +1,138 @@
+//! Linear population models.
+//!
+//! The state of the model is the number of items in each bucket (ie. fishes with a given timer,
+//! polymer pairs, ...) and every step moves (or multiplies) the items of each bucket into other
+//! buckets. The state after N steps is computed by exponentiation of the transition matrix, with
+//! arbitrary-precision counts.
+
+use num_bigint::BigUint;
+
+/// Square matrix where the cell `(to, from)` holds how many items of bucket `to` are generated by
+/// each item of bucket `from` in one step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransitionMatrix {
+    size: usize,
+    /// Row-major cells
+    cells: Vec<BigUint>,
+}
+
+impl TransitionMatrix {
+    #[must_use]
+    pub fn zeros(size: usize) -> Self {
+        Self {
+            size,
+            cells: vec![BigUint::default(); size * size],
+        }
+    }
+
+    #[must_use]
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = Self::zeros(size);
+        for bucket in 0..size {
+            matrix.cells[bucket * size + bucket] = BigUint::from(1_u8);
+        }
+        matrix
+    }
+
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Registers that each item of bucket `from` generates `count` items of bucket `to` in one step
+    ///
+    /// # Panics
+    ///
+    /// Will panic if any of the buckets is not smaller than the size of the matrix.
+    pub fn add(&mut self, from: usize, to: usize, count: u64) {
+        assert!(from < self.size && to < self.size, "Bucket out of bounds");
+        self.cells[to * self.size + from] += count;
+    }
+
+    /// Matrix describing the application of `other` followed by `self`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the matrices have different sizes.
+    #[must_use]
+    pub fn multiply(&self, other: &Self) -> Self {
+        assert_eq!(self.size, other.size, "Matrices with different sizes");
+        let mut result = Self::zeros(self.size);
+        for row in 0..self.size {
+            for k in 0..self.size {
+                let cell = &self.cells[row * self.size + k];
+                if *cell == BigUint::default() {
+                    continue;
+                }
+                for column in 0..self.size {
+                    let other_cell = &other.cells[k * self.size + column];
+                    if *other_cell != BigUint::default() {
+                        result.cells[row * self.size + column] += cell * other_cell;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Matrix describing `exponent` consecutive steps, via exponentiation by squaring
+    #[must_use]
+    pub fn pow(&self, mut exponent: u64) -> Self {
+        let mut result = Self::identity(self.size);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base);
+            }
+        }
+        result
+    }
+
+    /// Applies one step to `state`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `state` does not have a value for each bucket.
+    #[must_use]
+    pub fn apply(&self, state: &[BigUint]) -> Vec<BigUint> {
+        assert_eq!(
+            state.len(),
+            self.size,
+            "State with a wrong number of buckets"
+        );
+        (0..self.size)
+            .map(|row| {
+                self.cells[row * self.size..(row + 1) * self.size]
+                    .iter()
+                    .zip(state)
+                    .filter(|(cell, count)| {
+                        **cell != BigUint::default() && **count != BigUint::default()
+                    })
+                    .map(|(cell, count)| cell * count)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// State after `steps` steps.
+    ///
+    /// Exponentiation costs `O(size^3 log(steps))` while stepping costs `O(size^2 steps)`,
+    /// so the cheapest of the two is used.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `state` does not have a value for each bucket.
+    #[must_use]
+    pub fn state_after(&self, state: &[BigUint], steps: u64) -> Vec<BigUint> {
+        let squarings = u64::from(u64::BITS - steps.leading_zeros());
+        if 2 * squarings * (self.size as u64) < steps {
+            self.pow(steps).apply(state)
+        } else {
+            (0..steps).fold(state.to_vec(), |state, _| self.apply(&state))
+        }
+    }
+}